@@ -2,13 +2,82 @@ extern crate clap;
 extern crate omnidux_core;
 
 use clap::{App, Arg, SubCommand};
-use omnidux_core::{config, generator, debug};
+use std::path::Path;
+use std::process;
+
+use omnidux_core::{config, generator, graph, source, watch};
+use omnidux_core::generator::files::{ChangeKind, FileChange};
+
+/// Loads and validates the configuration, exits the process with readable errors on failure.
+fn load_config(source: &str, platform: &str) -> config::Configuration {
+  let config_path = format!("{s}/omnidux.{p}.yaml", s = source, p = platform);
+  match config::build_config_from_file(config_path.clone()) {
+    Ok(config) => config,
+    Err(err) => {
+      eprintln!("{}: {}", config_path, err);
+      process::exit(err.exit_code());
+    }
+  }
+}
+
+/// Maps a platform argument onto the configuration platform.
+fn parse_platform(platform: &str) -> config::Platform {
+  match platform {
+    "web" => config::Platform::Web,
+    "test" => config::Platform::Test,
+    _ => config::Platform::IOS,
+  }
+}
+
+/// Resolves the repo sources and generates the project with the backend of the target
+/// platform. A dry run only plans the changes, it neither touches the repo sources nor
+/// the lockfile, so `update` has no effect then.
+fn generate(source: &str, config: &config::Configuration, update: bool, workspace: bool, dry_run: bool)
+  -> Result<Vec<FileChange>, Box<dyn std::error::Error>> {
+  // Make sources of all repos available.
+  let repos = if dry_run {
+    source::plan_repos(config, Path::new(source))?
+  } else {
+    source::resolve_repos(config, Path::new(source), update)?
+  };
+
+  // Generate main library with the backend of the target platform.
+  let builder = generator::rust::RustProjectBuilder::new(config.name.clone())
+    .set_destination(format!("{d}/.omnidux", d = source))
+    .set_repos(repos)
+    .set_config(config.clone())
+    .set_workspace(workspace);
+
+  if dry_run { builder.plan() } else { builder.build() }
+}
+
+/// Prints the files that changed, or would change on a dry run.
+fn print_changes(changes: &[FileChange], dry_run: bool, diff: bool) {
+  for change in changes {
+    let action = match change.kind() {
+      ChangeKind::Create if dry_run => "would create",
+      ChangeKind::Update if dry_run => "would update",
+      ChangeKind::Delete if dry_run => "would delete",
+      ChangeKind::Create => "created",
+      ChangeKind::Update => "updated",
+      ChangeKind::Delete => "deleted",
+      ChangeKind::Unchanged => continue,
+    };
+
+    if diff {
+      print!("{}", change.diff());
+    } else {
+      println!("{} {}", action, change.path.display());
+    }
+  }
+}
 
 fn main() {
   let matches = App::new("omnidux")
     .version("0.1.0")
     .about("Does great things!")
     .author("Rene Eichhorn")
+    .after_help("ENVIRONMENT:\n    OMNIDUX_PATH    Folder containing the omnidux `core` and `sys` crates, defaults to the checkout\n                    around the omnidux executable")
     .subcommand(
       SubCommand::with_name("generate")
         .arg(Arg::with_name("platform")
@@ -17,7 +86,7 @@ fn main() {
           .help("Sets the target platform")
           .takes_value(true)
           .default_value("ios")
-          .possible_values(&["ios"]))
+          .possible_values(&["ios", "web", "test"]))
 
         .arg(Arg::with_name("source")
           .short("s")
@@ -25,6 +94,47 @@ fn main() {
           .help("Sets the source folder")
           .required(true)
           .takes_value(true))
+
+        .arg(Arg::with_name("update")
+          .short("u")
+          .long("update")
+          .help("Updates git repos to the latest commit of their reference instead of the locked one"))
+
+        .arg(Arg::with_name("workspace")
+          .short("w")
+          .long("workspace")
+          .help("Emits a workspace manifest covering the generated crate and all local repos"))
+
+        .arg(Arg::with_name("dry-run")
+          .long("dry-run")
+          .help("Lists the files that would change without writing them or fetching repos"))
+
+        .arg(Arg::with_name("diff")
+          .long("diff")
+          .help("Prints a unified diff of the files that would change without writing them or fetching repos"))
+    )
+    .subcommand(
+      SubCommand::with_name("dev")
+        .about("Regenerates the project whenever the configuration or a repo changes")
+        .arg(Arg::with_name("platform")
+          .short("p")
+          .long("platform")
+          .help("Sets the target platform")
+          .takes_value(true)
+          .default_value("ios")
+          .possible_values(&["ios", "web", "test"]))
+
+        .arg(Arg::with_name("source")
+          .short("s")
+          .long("source")
+          .help("Sets the source folder")
+          .required(true)
+          .takes_value(true))
+
+        .arg(Arg::with_name("workspace")
+          .short("w")
+          .long("workspace")
+          .help("Emits a workspace manifest covering the generated crate and all local repos"))
     )
     .subcommand(
       SubCommand::with_name("debug")
@@ -34,7 +144,56 @@ fn main() {
           .help("Sets the target platform")
           .takes_value(true)
           .default_value("ios")
-          .possible_values(&["ios"]))
+          .possible_values(&["ios", "web", "test"]))
+
+        .arg(Arg::with_name("source")
+          .short("s")
+          .long("source")
+          .help("Sets the source folder")
+          .required(true)
+          .takes_value(true))
+    )
+    .subcommand(
+      SubCommand::with_name("check")
+        .about("Validates the configuration and repo sources without generating anything")
+        .after_help("EXIT STATUS:
+    0    The configuration is valid
+    1    Any other failure, e.g. invalid arguments
+    2    The configuration could not be parsed
+    3    The configuration could not be read
+    4    The configuration is invalid")
+        .arg(Arg::with_name("platform")
+          .short("p")
+          .long("platform")
+          .help("Sets the target platform")
+          .takes_value(true)
+          .default_value("ios")
+          .possible_values(&["ios", "web", "test"]))
+
+        .arg(Arg::with_name("source")
+          .short("s")
+          .long("source")
+          .help("Sets the source folder")
+          .required(true)
+          .takes_value(true))
+
+        .arg(Arg::with_name("format")
+          .long("format")
+          .help("Sets the output format")
+          .takes_value(true)
+          .default_value("text")
+          .possible_values(&["text", "json"]))
+    )
+    .subcommand(
+      SubCommand::with_name("graph")
+        .about("Renders scopes, repos, threads and their schedule as a graph")
+        .arg(Arg::with_name("platform")
+          .short("p")
+          .long("platform")
+          .help("Sets the target platform")
+          .takes_value(true)
+          .default_value("ios")
+          .possible_values(&["ios", "web", "test"]))
 
         .arg(Arg::with_name("source")
           .short("s")
@@ -42,31 +201,224 @@ fn main() {
           .help("Sets the source folder")
           .required(true)
           .takes_value(true))
+
+        .arg(Arg::with_name("format")
+          .long("format")
+          .help("Sets the output format")
+          .takes_value(true)
+          .default_value("dot")
+          .possible_values(&["dot", "mermaid"]))
+
+        .arg(Arg::with_name("capsules")
+          .long("capsules")
+          .help("Adds edges for capsules read across repos, detected from the repo sources"))
+    )
+    .subcommand(
+      SubCommand::with_name("new")
+        .about("Creates the skeleton of a project or repo")
+        .subcommand(
+          SubCommand::with_name("project")
+            .arg(Arg::with_name("name")
+              .help("Name of the project folder")
+              .required(true))
+
+            .arg(Arg::with_name("platform")
+              .short("p")
+              .long("platform")
+              .help("Sets the target platform")
+              .takes_value(true)
+              .default_value("ios")
+              .possible_values(&["ios", "web", "test"]))
+        )
+        .subcommand(
+          SubCommand::with_name("repo")
+            .arg(Arg::with_name("name")
+              .help("Name of the repo")
+              .required(true))
+
+            .arg(Arg::with_name("platform")
+              .short("p")
+              .long("platform")
+              .help("Sets the target platform whose configuration the repo is registered in")
+              .takes_value(true)
+              .default_value("ios")
+              .possible_values(&["ios", "web", "test"]))
+
+            .arg(Arg::with_name("source")
+              .short("s")
+              .long("source")
+              .help("Sets the source folder")
+              .required(true)
+              .takes_value(true))
+        )
+    )
+    .subcommand(
+      SubCommand::with_name("config")
+        .about("Inspects the configuration format")
+        .subcommand(
+          SubCommand::with_name("schema")
+            .about("Prints the JSON Schema of omnidux.*.yaml files")
+        )
     )
     .get_matches(); 
 
   if let Some(matches) = matches.subcommand_matches("generate") {
     // Parse configuration of project.
+    let platform = matches.value_of("platform").unwrap();
+    let source = matches.value_of("source").unwrap();
+    let config = load_config(source, platform);
+
+    let dry_run = matches.is_present("dry-run") || matches.is_present("diff");
+    let workspace = matches.is_present("workspace");
+    let changes = generate(source, &config, matches.is_present("update"), workspace, dry_run)
+      .unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        process::exit(1);
+      });
+
+    print_changes(&changes, dry_run, matches.is_present("diff"));
+  } else if let Some(matches) = matches.subcommand_matches("dev") {
     let platform = matches.value_of("platform").unwrap();
     let source = matches.value_of("source").unwrap();
     let config_path = format!("{s}/omnidux.{p}.yaml", s = source, p = platform);
-    let config = config::build_config_from_file(config_path).unwrap();
+    let mut watcher = watch::Watcher::new(Vec::new(), watch::POLL_INTERVAL, watch::DEBOUNCE);
 
-    // Generate main library.
-    generator::rust::RustProjectBuilder::new(config.name.clone())
-      .set_destination(format!("{d}/.omnidux", d = source))
-      .build()
-      .unwrap();
+    loop {
+      // Errors are printed and fixed by the next change instead of stopping the watch.
+      let config = match config::build_config_from_file(config_path.clone()) {
+        Ok(config) => Some(config),
+        Err(err) => {
+          eprintln!("{}: {}", config_path, err);
+          None
+        },
+      };
+
+      if let Some(config) = &config {
+        match generate(source, config, false, matches.is_present("workspace"), false) {
+          Ok(changes) => {
+            print_changes(&changes, false, false);
+            println!("{}: generated, watching for changes", config_path);
+          },
+          Err(err) => eprintln!("{}", err),
+        }
+      }
 
-    print!("{:#?}", config);
+      // Repos added to the configuration are watched from now on.
+      watcher.set_paths(watch::watched_paths(Path::new(&config_path), config.as_ref()));
+      for file in watcher.wait() {
+        println!("changed {}", file.display());
+      }
+    }
   } else if let Some(matches) = matches.subcommand_matches("debug") {
     // Parse configuration of project.
+    let platform = matches.value_of("platform").unwrap();
+    let source = matches.value_of("source").unwrap();
+    let config = load_config(source, platform);
+
+    // The debugger runs from the generated crate, which links all configured repos.
+    let changes = generate(source, &config, false, false, false).unwrap_or_else(|err| {
+      eprintln!("{}", err);
+      process::exit(1);
+    });
+    print_changes(&changes, false, false);
+
+    let manifest = changes.iter()
+      .find(|change| change.path.ends_with(generator::platform::DEBUG_ENTRY))
+      .and_then(|change| change.path.parent()?.parent())
+      .map(|crate_path| crate_path.join("Cargo.toml"))
+      .unwrap();
+    let status = process::Command::new("cargo")
+      .args(&["run", "--quiet", "--example", "debug", "--manifest-path"])
+      .arg(&manifest)
+      .status()
+      .unwrap_or_else(|err| {
+        eprintln!("Failed to run cargo: {}", err);
+        process::exit(1);
+      });
+    process::exit(status.code().unwrap_or(1));
+  } else if let Some(matches) = matches.subcommand_matches("check") {
     let platform = matches.value_of("platform").unwrap();
     let source = matches.value_of("source").unwrap();
     let config_path = format!("{s}/omnidux.{p}.yaml", s = source, p = platform);
-    let config = config::build_config_from_file(config_path).unwrap();
 
-    // Run debug executor
-    debug::run_debugger(&config);
+    let result = config::check_config_file(config_path.clone());
+    let report = config::CheckReport::new(config_path.clone(), &result);
+    if matches.value_of("format") == Some("json") {
+      println!("{}", report.to_json());
+    } else {
+      match &result {
+        Ok(_) => println!("{}: ok", config_path),
+        Err(err) => eprintln!("{}: {}", config_path, err),
+      }
+    }
+
+    if let Err(err) = result {
+      process::exit(err.exit_code());
+    }
+  } else if let Some(matches) = matches.subcommand_matches("graph") {
+    let platform = matches.value_of("platform").unwrap();
+    let source = matches.value_of("source").unwrap();
+    let config = load_config(source, platform);
+
+    let capsule_reads = match matches.is_present("capsules") {
+      true => graph::detect_capsule_reads(&config, Path::new(source)),
+      false => Vec::new(),
+    };
+    let format = match matches.value_of("format") {
+      Some("mermaid") => graph::GraphFormat::Mermaid,
+      _ => graph::GraphFormat::Dot,
+    };
+    print!("{}", graph::render(&config, format, &capsule_reads));
+  } else if let Some(matches) = matches.subcommand_matches("new") {
+    let (changes, source, platform) = if let Some(matches) = matches.subcommand_matches("project") {
+      let name = matches.value_of("name").unwrap();
+      let platform = matches.value_of("platform").unwrap();
+      let platform = parse_platform(platform);
+      let changes = generator::scaffold::new_project(
+        Path::new("."),
+        name,
+        &platform,
+        &generator::template::default_omnidux_path(),
+      );
+      (changes, name.to_string(), platform)
+    } else if let Some(matches) = matches.subcommand_matches("repo") {
+      let source = matches.value_of("source").unwrap();
+      let platform = parse_platform(matches.value_of("platform").unwrap());
+      let config_file = format!("{s}/omnidux.{p}.yaml", s = source, p = platform.name());
+      let changes = generator::scaffold::new_repo(
+        Path::new(&config_file),
+        matches.value_of("name").unwrap(),
+        &generator::template::default_omnidux_path(),
+      );
+      (changes, source.to_string(), platform)
+    } else {
+      eprintln!("{}", matches.usage());
+      process::exit(1);
+    };
+
+    let changes = changes.unwrap_or_else(|err| {
+      eprintln!("{}", err);
+      process::exit(1);
+    });
+    for change in changes {
+      let action = match change.kind() {
+        ChangeKind::Create => "created",
+        ChangeKind::Update => "updated",
+        ChangeKind::Delete => "deleted",
+        ChangeKind::Unchanged => continue,
+      };
+      change.apply().unwrap_or_else(|err| {
+        eprintln!("{}: {}", change.path.display(), err);
+        process::exit(1);
+      });
+      println!("{} {}", action, change.path.display());
+    }
+
+    // The skeleton is expected to be a valid configuration.
+    load_config(&source, platform.name());
+  } else if let Some(matches) = matches.subcommand_matches("config") {
+    if matches.subcommand_matches("schema").is_some() {
+      println!("{}", config::schema::json_schema_string());
+    }
   }
 }
@@ -3,4 +3,7 @@ extern crate criterion;
 #[macro_use]
 extern crate omnidux_core;
 
-pub mod mpsc_threading;
\ No newline at end of file
+pub mod mpsc_threading;
+pub mod routing;
+
+criterion_main!(mpsc_threading::benches, routing::benches);
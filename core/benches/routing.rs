@@ -0,0 +1,173 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{channel, Sender};
+use std::thread;
+
+use criterion::{BenchmarkId, Criterion, Throughput};
+
+use omnidux_core::config::{build_config_from_str, Configuration};
+use omnidux_core::repo::Repository;
+use omnidux_core::scheduler::context::Context;
+use omnidux_core::task::Task;
+use omnidux_core::threads::Thread;
+
+/// Tasks scheduled per iteration, spread evenly across all threads.
+const TASKS: usize = 1000;
+const THREADS: &[usize] = &[2, 4, 8];
+
+#[allow(dead_code, non_snake_case)]
+mod noop {
+  use omnidux_core::scheduler::strategy::{ScheduleStrategy, take_first};
+  use omnidux_core::task::{TaskHandler, Task};
+  use omnidux_core::capsule::CapsuleContent;
+
+  impl_default_capsule! (NoopCapsule, usize, usize);
+
+  #[derive(Clone)]
+  pub struct Noop { uuid: usize }
+  impl TaskHandler for Noop {
+    type Input = ();
+    type Output = ();
+
+    fn handle(&self, _task: &Task, _input: &()) {}
+  }
+  impl_strategy! (Noop, take_first);
+
+  create_repo! {
+    tasks: [
+      Noop,
+    ],
+    capsules: [
+      NoopCapsule,
+    ]
+  }
+}
+
+fn config(threads: usize) -> Configuration {
+  let mut config = String::from("
+name: routing
+target:
+  platform: test
+  engine: native
+repos:
+  - type: local
+    source: ./noop
+setup:
+  - repo: noop
+    target: \"*\"
+threads:
+");
+  for i in 0..threads {
+    config.push_str(&format!("  - type: thread\n    name: worker{}\n    driver: mpsc-fifo\n", i));
+  }
+
+  build_config_from_str(&config).unwrap()
+}
+
+/// Creates a context with the noop repo that counts every handled task.
+fn context(config: &Configuration, senders: Vec<omnidux_core::threads::ThreadSender>) -> (Context, Arc<AtomicUsize>) {
+  let mut counter = 0usize;
+  let repos: Vec<Arc<dyn Repository + Send + Sync>> = vec![
+    Arc::new(noop::Repository::new(config.setup[0].clone(), &mut counter)),
+  ];
+
+  let handled = Arc::new(AtomicUsize::new(0));
+  let listener_handled = handled.clone();
+  let mut context = Context::new(repos, senders);
+  context.set_execution_listener(Arc::new(move |_, _| {
+    listener_handled.fetch_add(1, Ordering::SeqCst);
+  }));
+
+  (context, handled)
+}
+
+fn noop_task(thread: usize) -> Task {
+  Task {
+    execution_targets: Some(vec![thread]),
+    uuid: 0,
+    payload: Arc::new(()),
+    key: None,
+    priority: None,
+    completion: None,
+  }
+}
+
+/// Waits until the threads handled the expected number of tasks.
+fn wait_for(handled: &AtomicUsize, expected: usize) {
+  while handled.load(Ordering::SeqCst) < expected {
+    thread::yield_now();
+  }
+}
+
+/// Tasks are routed by the context to the sender of their target thread.
+fn routed(threads: usize) -> impl FnMut() {
+  let config = config(threads);
+  let mut workers: Vec<Thread> = config.threads.iter()
+    .enumerate()
+    .map(|(i, thread)| Thread::new(i, thread.clone()))
+    .collect();
+  let (context, handled) = context(&config, workers.iter().map(|worker| worker.create_sender()).collect());
+  for worker in &mut workers {
+    worker.spawn(&context);
+  }
+
+  let mut expected = 0;
+  move || {
+    for i in 0..TASKS {
+      context.schedule_on(noop_task(i % threads), vec![i % threads]);
+    }
+    expected += TASKS;
+    wait_for(&handled, expected);
+  }
+}
+
+/// Every task is sent to every thread, which drops the tasks targeting other threads.
+fn broadcast(threads: usize) -> impl FnMut() {
+  let config = config(threads);
+  let (context, handled) = context(&config, Vec::new());
+
+  let senders: Vec<Sender<Task>> = (0..threads).map(|uuid| {
+    let (sender, receiver) = channel::<Task>();
+    let context = context.clone();
+    thread::spawn(move || {
+      for task in receiver {
+        if task.execution_targets.as_ref().unwrap().contains(&uuid) {
+          context.handle_schedule(uuid, &task).unwrap();
+        }
+      }
+    });
+    sender
+  }).collect();
+
+  let mut expected = 0;
+  move || {
+    for i in 0..TASKS {
+      let task = noop_task(i % threads);
+      for sender in &senders {
+        sender.send(task.clone()).unwrap();
+      }
+    }
+    expected += TASKS;
+    wait_for(&handled, expected);
+  }
+}
+
+pub fn routing(c: &mut Criterion) {
+  let mut group = c.benchmark_group("task delivery");
+  group.throughput(Throughput::Elements(TASKS as u64));
+
+  for &threads in THREADS {
+    group.bench_with_input(BenchmarkId::new("routed", threads), &threads, |b, &threads| {
+      let mut run = routed(threads);
+      b.iter(|| run());
+    });
+    group.bench_with_input(BenchmarkId::new("broadcast", threads), &threads, |b, &threads| {
+      let mut run = broadcast(threads);
+      b.iter(|| run());
+    });
+  }
+
+  group.finish();
+}
+
+criterion_group!(benches, routing);
@@ -19,6 +19,18 @@ pub trait Capsule<K, V> where V: Copy + Sync + Send {
   fn set_content(&self, key: K, value: CapsuleContent<V>);
 }
 
+/// Untyped access to a capsule with keys and values in their text form, used by the debugger.
+/// Capsules that can't be represented as text keep the default implementation.
+pub trait DebugCapsule {
+  fn debug_get(&self, _key: &str) -> Result<String, String> {
+    Err("Capsule does not support debugging.".to_string())
+  }
+
+  fn debug_set(&self, _key: &str, _value: &str) -> Result<(), String> {
+    Err("Capsule does not support debugging.".to_string())
+  }
+}
+
 #[macro_export]
 macro_rules! capsule_get {
   ($context:ident, $repo:ident, $capsule:ident, $key:expr) => {
@@ -49,9 +61,37 @@ macro_rules! capsule_set {
 
 /// Implements the default capsule.
 /// The default implementation is not safe when used within multiple threads.
+/// Pass `inspectable` to let the debugger read and write the capsule, key and value
+/// types then need to implement `FromStr` and `Debug`.
 #[macro_export]
 macro_rules! impl_default_capsule {
   ($name: ident, $keyType: ident, $valueType: ident) => {
+    $crate::impl_default_capsule!(@capsule $name, $keyType, $valueType);
+
+    impl omnidux_core::capsule::DebugCapsule for $name {}
+  };
+  ($name: ident, $keyType: ident, $valueType: ident, inspectable) => {
+    $crate::impl_default_capsule!(@capsule $name, $keyType, $valueType);
+
+    impl omnidux_core::capsule::DebugCapsule for $name {
+      fn debug_get(&self, key: &str) -> Result<String, String> {
+        use omnidux_core::capsule::Capsule;
+
+        let key = key.parse::<$keyType>().map_err(|_| format!("Invalid key `{}`.", key))?;
+        Ok(format!("{:?}", self.request_content(&key)))
+      }
+
+      fn debug_set(&self, key: &str, value: &str) -> Result<(), String> {
+        use omnidux_core::capsule::Capsule;
+
+        let key = key.parse::<$keyType>().map_err(|_| format!("Invalid key `{}`.", key))?;
+        let value = value.parse::<$valueType>().map_err(|_| format!("Invalid value `{}`.", value))?;
+        self.set_content(key, CapsuleContent::Some(value));
+        Ok(())
+      }
+    }
+  };
+  (@capsule $name: ident, $keyType: ident, $valueType: ident) => {
     pub struct $name {
       map: std::sync::RwLock<
         std::collections::HashMap<
@@ -0,0 +1,102 @@
+use std::path::Path;
+
+use serde::Serialize;
+
+use super::{build_config_from_file, layering, BuildError, Configuration, RepoType};
+use super::diagnostic::Diagnostic;
+
+/// Exit code of a check whose configuration could not be parsed.
+pub const EXIT_PARSE: i32 = 2;
+/// Exit code of a check whose configuration could not be read.
+pub const EXIT_IO: i32 = 3;
+/// Exit code of a check that found validation errors, distinct from the `1` of any
+/// other failure such as invalid arguments.
+pub const EXIT_VALIDATION: i32 = 4;
+
+impl BuildError {
+  /// Short name of the error kind, as reported by `omnidux check --format json`.
+  pub fn kind(&self) -> &'static str {
+    match self {
+      BuildError::IOError(_) => "io",
+      BuildError::ParseError(_) => "parse",
+      BuildError::ValidationError(_) => "validation",
+    }
+  }
+
+  /// Process exit code that distinguishes the error kinds.
+  pub fn exit_code(&self) -> i32 {
+    match self {
+      BuildError::IOError(_) => EXIT_IO,
+      BuildError::ParseError(_) => EXIT_PARSE,
+      BuildError::ValidationError(_) => EXIT_VALIDATION,
+    }
+  }
+
+  /// Diagnostics of the error, IO and parse errors are reported as a single one.
+  pub fn diagnostics(&self) -> Vec<Diagnostic> {
+    match self {
+      BuildError::ValidationError(diagnostics) => diagnostics.clone(),
+      error => vec![Diagnostic::new("".to_string(), error.to_string())],
+    }
+  }
+}
+
+impl Configuration {
+  /// Checks that the source folder of every local repo exists inside of the project folder.
+  pub fn check_sources(&self, project: &Path) -> Vec<Diagnostic> {
+    self.repos.iter()
+      .enumerate()
+      .filter(|(_, repo)| repo.repo_type == RepoType::Local && !project.join(&repo.source).is_dir())
+      .map(|(i, repo)| Diagnostic::new(
+        format!("repos[{}].source", i),
+        format!("source folder `{}` does not exist", repo.source),
+      ))
+      .collect()
+  }
+}
+
+/// Builds and validates a configuration file like `build_config_from_file` and
+/// additionally checks that the sources of all local repos exist.
+pub fn check_config_file(file: String) -> Result<Configuration, BuildError> {
+  let config = build_config_from_file(file.clone())?;
+
+  let project = Path::new(&file).parent().unwrap_or_else(|| Path::new(""));
+  let mut diagnostics = config.check_sources(project);
+  if !diagnostics.is_empty() {
+    let layers = layering::load_layers(Path::new(&file))?;
+    layering::locate_layered_diagnostics(&layers, &config.origins, &mut diagnostics);
+    return Err(BuildError::ValidationError(diagnostics));
+  }
+
+  Ok(config)
+}
+
+/// Result of checking a single configuration file.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CheckReport {
+  pub file: String,
+  /// `ok` or the kind of the error.
+  pub status: &'static str,
+  pub diagnostics: Vec<Diagnostic>,
+}
+
+impl CheckReport {
+  pub fn new(file: String, result: &Result<Configuration, BuildError>) -> Self {
+    match result {
+      Ok(_) => CheckReport {
+        file: file,
+        status: "ok",
+        diagnostics: Vec::new(),
+      },
+      Err(error) => CheckReport {
+        file: file,
+        status: error.kind(),
+        diagnostics: error.diagnostics(),
+      },
+    }
+  }
+
+  pub fn to_json(&self) -> String {
+    serde_json::to_string_pretty(self).unwrap()
+  }
+}
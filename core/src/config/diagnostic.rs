@@ -0,0 +1,160 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use serde::Serialize;
+use yaml_rust::parser::{Event, MarkedEventReceiver, Parser};
+use yaml_rust::scanner::Marker;
+
+/// Position of a value inside of the configuration source.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct Location {
+  /// Line number, starting at 1.
+  pub line: usize,
+  /// Column number, starting at 1.
+  pub column: usize,
+}
+
+/// A single problem found while validating a configuration.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Diagnostic {
+  /// Path of the offending value, e.g. `setup[0].target`.
+  pub path: String,
+  /// Human readable description of the problem.
+  pub message: String,
+  /// File that defines the offending value, only known when built from files.
+  pub file: Option<String>,
+  /// Location inside of the yaml source, only known when built from a source.
+  pub location: Option<Location>,
+}
+
+impl Diagnostic {
+  pub fn new(path: String, message: String) -> Self {
+    Diagnostic {
+      path: path,
+      message: message,
+      file: None,
+      location: None,
+    }
+  }
+}
+
+impl fmt::Display for Diagnostic {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    if let Some(file) = &self.file {
+      write!(f, "{}:", file)?;
+    }
+
+    match self.location {
+      Some(location) => write!(f, "{}:{}: {}: {}", location.line, location.column, self.path, self.message),
+      None => write!(f, "{}: {}", self.path, self.message),
+    }
+  }
+}
+
+enum Frame {
+  Mapping { path: String, key: Option<String> },
+  Sequence { path: String, index: usize },
+}
+
+/// Collects the location of every value in a yaml document by its path.
+struct LocationCollector {
+  stack: Vec<Frame>,
+  locations: HashMap<String, Location>,
+}
+
+impl LocationCollector {
+  fn join(prefix: &str, key: &str) -> String {
+    if prefix.is_empty() {
+      key.to_string()
+    } else {
+      format!("{}.{}", prefix, key)
+    }
+  }
+
+  /// Resolves the path of the value that is about to be read.
+  /// Returns `None` if the next scalar is a mapping key.
+  fn next_value_path(&mut self) -> Option<String> {
+    match self.stack.last_mut() {
+      Some(Frame::Mapping { path, key }) => {
+        key.take().map(|key| LocationCollector::join(path, &key))
+      },
+      Some(Frame::Sequence { path, index }) => {
+        *index += 1;
+        Some(format!("{}[{}]", path, *index - 1))
+      },
+      None => Some("".to_string()),
+    }
+  }
+}
+
+impl MarkedEventReceiver for LocationCollector {
+  fn on_event(&mut self, event: Event, marker: Marker) {
+    let location = Location { line: marker.line(), column: marker.col() + 1 };
+
+    match event {
+      Event::Scalar(value, _, _, _) => {
+        match self.next_value_path() {
+          Some(path) => { self.locations.insert(path, location); },
+          None => {
+            if let Some(Frame::Mapping { path, key }) = self.stack.last_mut() {
+              self.locations.insert(LocationCollector::join(path, &value), location);
+              *key = Some(value);
+            }
+          },
+        }
+      },
+      Event::MappingStart(_) => {
+        let path = self.next_value_path().unwrap_or_default();
+        self.locations.entry(path.clone()).or_insert(location);
+        self.stack.push(Frame::Mapping { path: path, key: None });
+      },
+      Event::SequenceStart(_) => {
+        let path = self.next_value_path().unwrap_or_default();
+        self.locations.entry(path.clone()).or_insert(location);
+        self.stack.push(Frame::Sequence { path: path, index: 0 });
+      },
+      Event::MappingEnd | Event::SequenceEnd => {
+        self.stack.pop();
+      },
+      _ => {},
+    }
+  }
+}
+
+/// Collects the location of every value inside of the yaml source by its path.
+pub fn collect_locations(source: &str) -> HashMap<String, Location> {
+  let mut collector = LocationCollector {
+    stack: Vec::new(),
+    locations: HashMap::new(),
+  };
+
+  let mut parser = Parser::new(source.chars());
+  match parser.load(&mut collector, false) {
+    Ok(_) => collector.locations,
+    Err(_) => HashMap::new(),
+  }
+}
+
+/// Looks up the location of a path, falling back to its closest parent.
+pub fn lookup_location(locations: &HashMap<String, Location>, path: &str) -> Option<Location> {
+  let mut path = path;
+  loop {
+    if let Some(location) = locations.get(path) {
+      return Some(*location);
+    }
+
+    match path.rfind(&['.', '['][..]) {
+      Some(index) => path = &path[..index],
+      None => return None,
+    }
+  }
+}
+
+/// Attaches source locations to the diagnostics by looking up their paths
+/// inside of the yaml source.
+pub fn locate_diagnostics(source: &str, diagnostics: &mut [Diagnostic]) {
+  let locations = collect_locations(source);
+  for diagnostic in diagnostics.iter_mut() {
+    diagnostic.location = lookup_location(&locations, &diagnostic.path);
+  }
+}
@@ -0,0 +1,266 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde_yaml::{Mapping, Value};
+
+use super::BuildError;
+use super::diagnostic::{self, Diagnostic, Location};
+use super::layout::quote_bare_wildcards;
+
+/// Name of the shared base configuration that is implicitly extended by every
+/// platform configuration in the same folder.
+pub const BASE_CONFIG_FILE: &str = "omnidux.yaml";
+
+/// Key that lets a configuration extend another file, relative to itself.
+pub const EXTENDS_KEY: &str = "extends";
+
+/// Describes where a value of the merged configuration was defined.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Origin {
+  /// File that defined the value.
+  pub file: PathBuf,
+  /// Path of the value inside of that file.
+  pub path: String,
+}
+
+/// A single configuration file taking part in the merge.
+pub struct Layer {
+  pub file: PathBuf,
+  pub content: String,
+  pub value: Value,
+}
+
+/// Result of merging all layers of a configuration.
+pub struct Merged {
+  pub value: Value,
+  pub origins: HashMap<String, Origin>,
+}
+
+/// Returns the field that identifies items of a list, lists without key are replaced as a whole.
+///
+/// * `repos` are keyed by `source`.
+/// * `threads` are keyed by `name`.
+/// * `setup` entries and `scopes` (on any level) are keyed by `repo`.
+///
+/// Items of keyed lists with the same key are deep merged, new items are appended.
+pub fn list_key(path: &str) -> Option<&'static str> {
+  let mut normalized = String::new();
+  let mut in_index = false;
+  for c in path.chars() {
+    match c {
+      '[' => in_index = true,
+      ']' => in_index = false,
+      _ if !in_index => normalized.push(c),
+      _ => {},
+    }
+  }
+
+  match normalized.as_str() {
+    "repos" => Some("source"),
+    "threads" | "application.threads" => Some("name"),
+    "setup" | "application.repos" => Some("repo"),
+    p if p == "scopes" || p.ends_with(".scopes") => Some("repo"),
+    _ => None,
+  }
+}
+
+fn join(prefix: &str, key: &str) -> String {
+  if prefix.is_empty() {
+    key.to_string()
+  } else {
+    format!("{}.{}", prefix, key)
+  }
+}
+
+fn key_to_string(key: &Value) -> String {
+  match key {
+    Value::String(key) => key.clone(),
+    other => serde_yaml::to_string(other)
+      .map(|key| key.trim_start_matches("---").trim().to_string())
+      .unwrap_or_default(),
+  }
+}
+
+fn load_layer(file: &Path) -> Result<Layer, BuildError> {
+  let content = quote_bare_wildcards(&fs::read_to_string(file)?);
+  let value = serde_yaml::from_str(&content)?;
+
+  Ok(Layer {
+    file: file.to_path_buf(),
+    content: content,
+    value: value,
+  })
+}
+
+/// Loads the given configuration file and every file it extends, ordered from
+/// the outermost base to the given file.
+pub fn load_layers(file: &Path) -> Result<Vec<Layer>, BuildError> {
+  let mut layers = Vec::new();
+  // Canonical paths of the loaded layers, different paths may lead to the same file.
+  let mut visited = Vec::new();
+  let mut next = Some(file.to_path_buf());
+
+  while let Some(file) = next.take() {
+    let canonical = fs::canonicalize(&file)?;
+    if visited.contains(&canonical) {
+      return Err(BuildError::ValidationError(vec![Diagnostic::new(
+        EXTENDS_KEY.to_string(),
+        format!("{} extends itself", file.display()),
+      )]));
+    }
+
+    let mut layer = load_layer(&file)?;
+    let folder = file.parent().map(Path::to_path_buf).unwrap_or_default();
+
+    let extends = match &mut layer.value {
+      Value::Mapping(mapping) => mapping.remove(&Value::String(EXTENDS_KEY.to_string())),
+      _ => None,
+    };
+
+    next = match extends {
+      Some(Value::String(base)) => Some(folder.join(base)),
+      Some(_) => {
+        return Err(BuildError::ValidationError(vec![Diagnostic::new(
+          EXTENDS_KEY.to_string(),
+          "`extends` must be a path to another configuration".to_string(),
+        )]));
+      },
+      None => {
+        let base = folder.join(BASE_CONFIG_FILE);
+        let is_base = file.file_name() == Some(BASE_CONFIG_FILE.as_ref());
+        if !is_base && layers.is_empty() && base.exists() { Some(base) } else { None }
+      },
+    };
+
+    visited.push(canonical);
+    layers.push(layer);
+  }
+
+  layers.reverse();
+  Ok(layers)
+}
+
+/// Records the origin of the value and all of its children.
+fn record(value: &Value, path: &str, origin: &Origin, origins: &mut HashMap<String, Origin>) {
+  origins.insert(path.to_string(), origin.clone());
+
+  match value {
+    Value::Mapping(mapping) => {
+      for (key, child) in mapping.iter() {
+        let key = key_to_string(key);
+        record(child, &join(path, &key), &Origin {
+          file: origin.file.clone(),
+          path: join(&origin.path, &key),
+        }, origins);
+      }
+    },
+    Value::Sequence(sequence) => {
+      for (i, child) in sequence.iter().enumerate() {
+        record(child, &format!("{}[{}]", path, i), &Origin {
+          file: origin.file.clone(),
+          path: format!("{}[{}]", origin.path, i),
+        }, origins);
+      }
+    },
+    _ => {},
+  }
+}
+
+/// Deep merges the overlay into the base value.
+fn merge(base: &mut Value, overlay: Value, path: &str, origin: &Origin, origins: &mut HashMap<String, Origin>) {
+  match (base, overlay) {
+    (Value::Mapping(base), Value::Mapping(overlay)) => {
+      for (key, child) in overlay {
+        let name = key_to_string(&key);
+        let child_path = join(path, &name);
+        let child_origin = Origin { file: origin.file.clone(), path: join(&origin.path, &name) };
+
+        match base.get_mut(&key) {
+          Some(existing) => merge(existing, child, &child_path, &child_origin, origins),
+          None => {
+            record(&child, &child_path, &child_origin, origins);
+            base.insert(key, child);
+          },
+        }
+      }
+    },
+    (Value::Sequence(base), Value::Sequence(overlay)) if list_key(path).is_some() => {
+      let item_key = Value::String(list_key(path).unwrap().to_string());
+      let key_of = |item: &Value| item.as_mapping().and_then(|item: &Mapping| item.get(&item_key).cloned());
+
+      for (j, item) in overlay.into_iter().enumerate() {
+        let item_origin = Origin { file: origin.file.clone(), path: format!("{}[{}]", origin.path, j) };
+        let existing = key_of(&item).and_then(|key| base.iter().position(|x| key_of(x) == Some(key.clone())));
+
+        match existing {
+          Some(i) => merge(&mut base[i], item, &format!("{}[{}]", path, i), &item_origin, origins),
+          None => {
+            record(&item, &format!("{}[{}]", path, base.len()), &item_origin, origins);
+            base.push(item);
+          },
+        }
+      }
+    },
+    (base, overlay) => {
+      origins.retain(|key, _| !(key.starts_with(path) && key[path.len()..].starts_with(&['.', '['][..])));
+      record(&overlay, path, origin, origins);
+      *base = overlay;
+    },
+  }
+}
+
+/// Merges all layers, later layers take precedence over earlier ones.
+pub fn merge_layers(layers: &[Layer]) -> Merged {
+  let mut value = Value::Mapping(Mapping::new());
+  let mut origins = HashMap::new();
+
+  for layer in layers {
+    let origin = Origin { file: layer.file.clone(), path: "".to_string() };
+    merge(&mut value, layer.value.clone(), "", &origin, &mut origins);
+  }
+
+  Merged {
+    value: value,
+    origins: origins,
+  }
+}
+
+/// Looks up the origin of a path, falling back to its closest parent.
+/// The returned origin points at the exact path inside of the defining file.
+pub fn find_origin(origins: &HashMap<String, Origin>, path: &str) -> Option<Origin> {
+  let mut prefix = path;
+  loop {
+    if let Some(origin) = origins.get(prefix) {
+      return Some(Origin {
+        file: origin.file.clone(),
+        path: format!("{}{}", origin.path, &path[prefix.len()..]).trim_start_matches('.').to_string(),
+      });
+    }
+
+    match prefix.rfind(&['.', '['][..]) {
+      Some(index) => prefix = &prefix[..index],
+      None => return None,
+    }
+  }
+}
+
+/// Attaches files and source locations to the diagnostics of a merged configuration.
+pub fn locate_layered_diagnostics(layers: &[Layer], origins: &HashMap<String, Origin>, diagnostics: &mut [Diagnostic]) {
+  let locations: Vec<HashMap<String, Location>> = layers
+    .iter()
+    .map(|layer| diagnostic::collect_locations(&layer.content))
+    .collect();
+
+  for diagnostic in diagnostics.iter_mut() {
+    let origin = match find_origin(origins, &diagnostic.path) {
+      Some(origin) => origin,
+      None => continue,
+    };
+
+    if let Some(i) = layers.iter().position(|layer| layer.file == origin.file) {
+      diagnostic.file = Some(origin.file.display().to_string());
+      diagnostic.location = diagnostic::lookup_location(&locations[i], &origin.path);
+    }
+  }
+}
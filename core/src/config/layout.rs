@@ -0,0 +1,158 @@
+use serde::{Serialize, Deserialize};
+use schemars::JsonSchema;
+use std::collections::HashMap;
+
+use super::{Configuration, Target, Repo, Scope, Thread, Schedule};
+use super::diagnostic::Diagnostic;
+
+/// Version of the legacy flat layout with top level `threads` and `setup`.
+pub const LEGACY_VERSION: u32 = 1;
+
+/// Version of the current layout that nests `threads` and `repos` under `application`.
+pub const CURRENT_VERSION: u32 = 2;
+
+/// Application section of the current layout.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Application {
+  pub threads: Vec<Thread>,
+  pub repos: Vec<Schedule>,
+}
+
+/// Configuration as it is written in the yaml file, covering every known layout.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct RawConfiguration {
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub version: Option<u32>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub name: Option<String>,
+  pub target: Target,
+  #[serde(default)]
+  pub repos: Vec<Repo>,
+  #[serde(default)]
+  pub scopes: Vec<Scope>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub threads: Option<Vec<Thread>>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub setup: Option<Vec<Schedule>>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub application: Option<Application>,
+}
+
+impl RawConfiguration {
+  /// Detects the layout version, either explicitly set or derived from the used keys.
+  pub fn detect_version(&self) -> u32 {
+    match self.version {
+      Some(version) => version,
+      None if self.application.is_some() => CURRENT_VERSION,
+      None => LEGACY_VERSION,
+    }
+  }
+
+  /// Migrates the raw configuration of any layout into the unified configuration.
+  pub fn into_configuration(self) -> Result<Configuration, Vec<Diagnostic>> {
+    let version = self.detect_version();
+    let mut diagnostics = Vec::new();
+
+    let (threads, setup) = match version {
+      LEGACY_VERSION => {
+        if self.application.is_some() {
+          diagnostics.push(Diagnostic::new(
+            "application".to_string(),
+            format!("`application` is not supported by layout version {}", LEGACY_VERSION),
+          ));
+        }
+        if self.threads.is_none() {
+          diagnostics.push(Diagnostic::new("threads".to_string(), "missing field `threads`".to_string()));
+        }
+        if self.setup.is_none() {
+          diagnostics.push(Diagnostic::new("setup".to_string(), "missing field `setup`".to_string()));
+        }
+        (self.threads.unwrap_or_default(), self.setup.unwrap_or_default())
+      },
+      CURRENT_VERSION => {
+        for key in [("threads", self.threads.is_some()), ("setup", self.setup.is_some())].iter() {
+          if key.1 {
+            diagnostics.push(Diagnostic::new(
+              key.0.to_string(),
+              format!("`{}` must be nested under `application` in layout version {}", key.0, CURRENT_VERSION),
+            ));
+          }
+        }
+        match self.application {
+          Some(application) => (application.threads, application.repos),
+          None => {
+            diagnostics.push(Diagnostic::new("application".to_string(), "missing field `application`".to_string()));
+            (Vec::new(), Vec::new())
+          },
+        }
+      },
+      _ => {
+        diagnostics.push(Diagnostic::new(
+          "version".to_string(),
+          format!("unsupported layout version {}, expected at most {}", version, CURRENT_VERSION),
+        ));
+        (Vec::new(), Vec::new())
+      },
+    };
+
+    if !diagnostics.is_empty() {
+      return Err(diagnostics);
+    }
+
+    Ok(Configuration {
+      version: version,
+      name: self.name.unwrap_or_default(),
+      target: self.target,
+      repos: self.repos,
+      scopes: self.scopes,
+      threads: threads,
+      setup: setup,
+      origins: HashMap::new(),
+      manifests: HashMap::new(),
+    })
+  }
+}
+
+impl From<Configuration> for RawConfiguration {
+  /// Converts the configuration back into the current layout.
+  fn from(config: Configuration) -> Self {
+    RawConfiguration {
+      version: Some(CURRENT_VERSION),
+      name: if config.name.is_empty() { None } else { Some(config.name) },
+      target: config.target,
+      repos: config.repos,
+      scopes: config.scopes,
+      threads: None,
+      setup: None,
+      application: Some(Application {
+        threads: config.threads,
+        repos: config.setup,
+      }),
+    }
+  }
+}
+
+/// Quotes bare `*` values which are used as wildcard targets but would otherwise
+/// be read as yaml aliases. Line endings are kept, so positions of later lines don't move.
+pub fn quote_bare_wildcards(content: &str) -> String {
+  content
+    .split('\n')
+    .map(|line| {
+      let (line, ending) = if line.ends_with('\r') {
+        (&line[..line.len() - 1], "\r")
+      } else {
+        (line, "")
+      };
+      let code_end = line.find(" #").unwrap_or(line.len());
+      let (code, comment) = line.split_at(code_end);
+      let trimmed = code.trim_end();
+
+      if trimmed.ends_with(": *") || trimmed.trim_start() == "- *" {
+        format!("{}\"*\"{}{}", &trimmed[..trimmed.len() - 1], comment, ending)
+      } else {
+        format!("{}{}", line, ending)
+      }
+    })
+    .collect::<Vec<_>>()
+    .join("\n")
+}
@@ -0,0 +1,197 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Serialize, Deserialize};
+use schemars::JsonSchema;
+
+use super::{BuildError, Configuration, Platform, Scope};
+use super::diagnostic::{self, Diagnostic};
+
+/// Name of the manifest file inside of each repo source folder.
+pub const MANIFEST_FILE: &str = "omnidux.repo.yaml";
+
+/// A task exported by a repo.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct TaskManifest {
+  pub name: String,
+  /// Function of `omnidux_core::scheduler::strategy` used by `impl_strategy!`.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub strategy: Option<String>,
+}
+
+/// A capsule exported by a repo.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct CapsuleManifest {
+  pub name: String,
+  /// Rust type of the capsule keys.
+  pub key: String,
+  /// Rust type of the capsule values.
+  pub value: String,
+}
+
+/// A capsule of another repo that is read by this repo.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct CapsuleRequirement {
+  pub repo: String,
+  pub capsule: String,
+}
+
+/// Describes the contents of a repo, read from its `omnidux.repo.yaml`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct RepoManifest {
+  /// Name of the crate that calls `create_repo!`.
+  #[serde(rename = "crate")]
+  pub crate_name: String,
+  #[serde(default)]
+  pub tasks: Vec<TaskManifest>,
+  #[serde(default)]
+  pub capsules: Vec<CapsuleManifest>,
+  #[serde(default)]
+  pub requires: Vec<CapsuleRequirement>,
+  /// Repos whose scope this repo may be nested in, any scope if empty.
+  #[serde(default)]
+  pub scopes: Vec<String>,
+  /// Crate features enabled by the generated project, by platform name.
+  #[serde(default)]
+  pub features: BTreeMap<String, Vec<String>>,
+  /// Manifest file the manifest was read from.
+  #[serde(skip)]
+  pub file: PathBuf,
+  #[serde(skip)]
+  content: String,
+}
+
+impl RepoManifest {
+  /// Whether the repo exports a capsule with the given name.
+  pub fn has_capsule(&self, name: &str) -> bool {
+    self.capsules.iter().any(|capsule| capsule.name == name)
+  }
+
+  /// Whether the repo exports a task with the given name.
+  pub fn has_task(&self, name: &str) -> bool {
+    self.tasks.iter().any(|task| task.name == name)
+  }
+}
+
+/// Reads the manifest of a repo source folder, a folder without manifest has none.
+pub fn load_manifest(folder: &Path) -> Result<Option<RepoManifest>, BuildError> {
+  let file = folder.join(MANIFEST_FILE);
+  if !file.exists() {
+    return Ok(None);
+  }
+
+  let content = fs::read_to_string(&file)?;
+  let mut manifest: RepoManifest = serde_yaml::from_str(&content)?;
+  manifest.file = file;
+  manifest.content = content;
+  Ok(Some(manifest))
+}
+
+/// Collects the repos that enclose the given repo within the scope tree.
+fn find_ancestors<'a>(scopes: &'a [Scope], repo: &str, path: &mut Vec<&'a str>) -> Option<Vec<&'a str>> {
+  for scope in scopes {
+    if scope.repo == repo {
+      return Some(path.clone());
+    }
+
+    if let Some(children) = &scope.scopes {
+      path.push(&scope.repo);
+      let found = find_ancestors(children, repo, path);
+      path.pop();
+      if found.is_some() {
+        return found;
+      }
+    }
+  }
+
+  None
+}
+
+impl Configuration {
+  /// Loads the manifests of all repos whose sources are available inside of the project folder.
+  pub fn load_manifests(&mut self, project: &Path) -> Result<(), BuildError> {
+    for repo in &self.repos {
+      if let Some(manifest) = load_manifest(&project.join(repo.path()))? {
+        self.manifests.insert(repo.name(), manifest);
+      }
+    }
+
+    Ok(())
+  }
+
+  /// Checks the loaded manifests against each other and against the configuration.
+  pub fn validate_manifests(&self) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+
+    let mut names: Vec<&String> = self.manifests.keys().collect();
+    names.sort();
+
+    for name in names {
+      let manifest = &self.manifests[name];
+      let mut local = Vec::new();
+
+      if manifest.crate_name.is_empty() {
+        local.push(Diagnostic::new("crate".to_string(), "crate name must not be empty".to_string()));
+      }
+
+      let mut tasks = HashSet::new();
+      for (i, task) in manifest.tasks.iter().enumerate() {
+        if !tasks.insert(&task.name) {
+          local.push(Diagnostic::new(format!("tasks[{}].name", i), format!("task `{}` is declared more than once", task.name)));
+        }
+      }
+
+      let mut capsules = HashSet::new();
+      for (i, capsule) in manifest.capsules.iter().enumerate() {
+        if !capsules.insert(&capsule.name) {
+          local.push(Diagnostic::new(format!("capsules[{}].name", i), format!("capsule `{}` is declared more than once", capsule.name)));
+        }
+      }
+
+      for (i, requirement) in manifest.requires.iter().enumerate() {
+        if !self.has_repo(&requirement.repo) {
+          local.push(Diagnostic::new(
+            format!("requires[{}].repo", i),
+            format!("requires capsule of unknown repo `{}`", requirement.repo),
+          ));
+        } else if let Some(other) = self.manifests.get(&requirement.repo) {
+          if !other.has_capsule(&requirement.capsule) {
+            local.push(Diagnostic::new(
+              format!("requires[{}].capsule", i),
+              format!("repo `{}` does not export capsule `{}`", requirement.repo, requirement.capsule),
+            ));
+          }
+        }
+      }
+
+      for platform in manifest.features.keys() {
+        if ![Platform::Test, Platform::IOS, Platform::Web].iter().any(|known| known.name() == platform) {
+          local.push(Diagnostic::new(
+            format!("features.{}", platform),
+            format!("unknown platform `{}`", platform),
+          ));
+        }
+      }
+
+      if !manifest.scopes.is_empty() {
+        if let Some(ancestors) = find_ancestors(&self.scopes, name, &mut Vec::new()) {
+          if !ancestors.iter().any(|ancestor| manifest.scopes.iter().any(|allowed| allowed == ancestor)) {
+            local.push(Diagnostic::new(
+              "scopes".to_string(),
+              format!("repo `{}` may only be nested in the scope of {}", name, manifest.scopes.join(", ")),
+            ));
+          }
+        }
+      }
+
+      diagnostic::locate_diagnostics(&manifest.content, &mut local);
+      for diagnostic in local.iter_mut() {
+        diagnostic.file = Some(manifest.file.display().to_string());
+      }
+      diagnostics.append(&mut local);
+    }
+
+    diagnostics
+  }
+}
@@ -1,44 +1,126 @@
 use serde::{Serialize, Deserialize};
-use std::fs;
+use schemars::JsonSchema;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+pub mod check;
+pub mod diagnostic;
+pub mod layering;
+pub mod layout;
+pub mod manifest;
+pub mod scale;
+pub mod schema;
+pub mod selector;
+pub mod validate;
+
+pub use check::{check_config_file, CheckReport};
+pub use diagnostic::{Diagnostic, Location};
+pub use layering::Origin;
+pub use layout::{RawConfiguration, CURRENT_VERSION, LEGACY_VERSION};
+pub use manifest::{RepoManifest, MANIFEST_FILE};
+pub use scale::{Scale, ScaleContext, ResolvedScale};
+pub use selector::TargetExpression;
+
+/// Folder inside of the project that holds the checkouts of all git repos.
+pub const CHECKOUT_FOLDER: &str = ".omnidux/checkouts";
 
 fn default_as_false() -> bool {
   false
 }
 
-#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub enum Platform {
   #[serde(rename = "test")]
   Test,
   #[serde(rename = "ios")]
   IOS,
+  #[serde(rename = "web")]
+  Web,
+}
+
+impl Platform {
+  /// Every supported platform.
+  pub const ALL: [Platform; 3] = [Platform::Test, Platform::IOS, Platform::Web];
+
+  /// Name of the platform as written in the configuration.
+  pub fn name(&self) -> &'static str {
+    match self {
+      Platform::Test => "test",
+      Platform::IOS => "ios",
+      Platform::Web => "web",
+    }
+  }
 }
 
-#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct Target {
   pub platform: Platform,
   pub engine: String,
 }
 
-#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type")]
 pub enum RepoType {
+  /// A repo inside of the project folder.
   #[serde(rename = "local")]
   Local,
+  /// A repo inside of a git repository, checked out to `.omnidux/checkouts`.
+  /// At most one of `rev`, `branch` and `tag` may be set, defaults to the remote `HEAD`.
+  #[serde(rename = "git")]
+  Git {
+    url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    rev: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    branch: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tag: Option<String>,
+  },
 }
 
-#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct Repo {
-  #[serde(rename = "type")]
+  #[serde(flatten)]
   pub repo_type: RepoType,
+  /// Path of the repo, relative to the project for local repos and relative to the
+  /// repository root for git repos.
+  #[serde(default)]
   pub source: String,
 }
 
-#[derive(Debug, PartialEq, Serialize, Deserialize)]
+impl Repo {
+  /// Name of the repo as referenced by `scopes` and `setup`, derived from the last
+  /// component of its source, or from the url for git repos without source.
+  pub fn name(&self) -> String {
+    let source = Path::new(&self.source).file_name();
+    match (&self.repo_type, source) {
+      (_, Some(name)) => name.to_string_lossy().into_owned(),
+      (RepoType::Git { url, .. }, None) => {
+        let url = url.trim_end_matches('/');
+        let name = url.rsplit(&['/', ':'][..]).next().unwrap_or(url);
+        name.trim_end_matches(".git").to_string()
+      },
+      (RepoType::Local, None) => self.source.clone(),
+    }
+  }
+
+  /// Folder of the repo sources relative to the project, git repos live inside of their checkout.
+  pub fn path(&self) -> PathBuf {
+    match self.repo_type {
+      RepoType::Local => PathBuf::from(&self.source),
+      RepoType::Git { .. } => Path::new(CHECKOUT_FOLDER).join(self.name()).join(&self.source),
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct Scope {
   pub repo: String,
   pub scopes: Option<Vec<Scope>>,
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub enum ThreadType {
   #[serde(rename = "webworker")]
   WebWorker,
@@ -49,68 +131,178 @@ pub enum ThreadType {
 }
 
 #[allow(non_camel_case_types)]
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub enum ThreadDriver {
   #[serde(rename = "mpsc-fifo")]
   MPSC_FIFO,
   #[serde(rename = "direct")]
   Direct,
+  #[serde(rename = "worker-direct")]
+  WorkerDirect,
   #[serde(rename = "default")]
   Default,
+  /// Handles the most urgent task first, see `threads::priority::PriorityQueue`.
+  #[serde(rename = "priority")]
+  Priority,
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+impl ThreadDriver {
+  /// Name of the driver as written in the configuration.
+  pub fn name(&self) -> &'static str {
+    match self {
+      ThreadDriver::MPSC_FIFO => "mpsc-fifo",
+      ThreadDriver::Direct => "direct",
+      ThreadDriver::WorkerDirect => "worker-direct",
+      ThreadDriver::Default => "default",
+      ThreadDriver::Priority => "priority",
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct Thread {
   #[serde(rename = "type")]
   pub thread_type: ThreadType,
   pub name: String,
   pub driver: ThreadDriver,
+  /// Free form tags that can be used by schedule targets, e.g. `tag:background`.
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub tags: Vec<String>,
 }
 
-#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+/// Selects the threads a repo is scheduled on, see `selector::TargetExpression`
+/// for the supported syntax.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, JsonSchema)]
 #[serde(untagged)]
 pub enum ScheduleTarget {
   Expression (String),
   Multiple (Vec<String>),
 }
 
-#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, JsonSchema)]
 #[serde(untagged)]
 pub enum ScheduleScaleValue {
   Static (u8),
   Dynamic (String),
 }
 
-fn default_as_one () -> ScheduleScaleValue { ScheduleScaleValue::Static(0) }
+/// Urgency of a task, only threads with the `priority` driver take it into account.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, JsonSchema)]
+pub enum TaskPriority {
+  /// Has to be handled before anything else, e.g. input feedback.
+  #[serde(rename = "immediate")]
+  Immediate,
+  /// The user waits for the result.
+  #[serde(rename = "user-blocking")]
+  UserBlocking,
+  #[serde(rename = "normal")]
+  Normal,
+  /// Background work that only runs if nothing else is queued.
+  #[serde(rename = "idle")]
+  Idle,
+}
+
+impl TaskPriority {
+  /// All priorities, most urgent first.
+  pub const ALL: [TaskPriority; 4] = [
+    TaskPriority::Immediate,
+    TaskPriority::UserBlocking,
+    TaskPriority::Normal,
+    TaskPriority::Idle,
+  ];
+
+  fn is_normal(&self) -> bool {
+    *self == TaskPriority::Normal
+  }
+}
 
-#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+impl Default for TaskPriority {
+  fn default() -> Self {
+    TaskPriority::Normal
+  }
+}
+
+/// A schedule without an explicit minimum runs on at least one thread.
+fn default_as_one () -> ScheduleScaleValue { ScheduleScaleValue::Static(1) }
+
+/// A schedule without an explicit maximum may scale up to all of its targets.
+fn default_as_all () -> ScheduleScaleValue { ScheduleScaleValue::Dynamic("all".to_string()) }
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct Schedule {
   pub repo: String,
   pub target: ScheduleTarget,
   #[serde(default="default_as_one")]
   pub min: ScheduleScaleValue,
-  #[serde(default="default_as_one")]
+  #[serde(default="default_as_all")]
   pub max: ScheduleScaleValue,
   #[serde(default="default_as_false")]
   pub debug: bool,
+  /// Priority of the tasks of the repo that don't set one.
+  #[serde(default, skip_serializing_if = "TaskPriority::is_normal")]
+  pub priority: TaskPriority,
+  /// Indices into `Configuration::threads` selected by `target`, resolved while
+  /// building the configuration.
+  #[serde(skip)]
+  pub target_threads: Vec<usize>,
 }
 
-#[derive(Debug, PartialEq, Serialize, Deserialize)]
+/// Unified configuration, independent of the layout version it was read from.
+#[derive(Debug, Clone, PartialEq)]
 pub struct Configuration {
+  /// Layout version of the source the configuration was read from.
+  pub version: u32,
   pub name: String,
   pub target: Target,
   pub repos: Vec<Repo>,
   pub scopes: Vec<Scope>,
   pub threads: Vec<Thread>,
   pub setup: Vec<Schedule>,
+  /// Where each value was defined, keyed by its path. Only known when built from files.
+  pub origins: HashMap<String, Origin>,
+  /// Manifests of all repos that provide one, keyed by repo name. Only known when built from files.
+  pub manifests: HashMap<String, RepoManifest>,
+}
+
+impl Configuration {
+  /// Resolves the target expression of every schedule into concrete thread indices.
+  pub fn resolve_targets(&mut self) {
+    for schedule in self.setup.iter_mut() {
+      schedule.target_threads = schedule.target.resolve(&self.threads).unwrap_or_default();
+    }
+  }
+
+  /// Returns the file and path that defined the value at the given path.
+  pub fn origin_of(&self, path: &str) -> Option<Origin> {
+    layering::find_origin(&self.origins, path)
+  }
 }
 
 #[derive(Debug)]
 pub enum BuildError {
   IOError (std::io::Error),
   ParseError (serde_yaml::Error),
+  ValidationError (Vec<Diagnostic>),
+}
+
+impl fmt::Display for BuildError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      BuildError::IOError(error) => write!(f, "Failed to read configuration: {}", error),
+      BuildError::ParseError(error) => write!(f, "Failed to parse configuration: {}", error),
+      BuildError::ValidationError(diagnostics) => {
+        write!(f, "Configuration is invalid:")?;
+        for diagnostic in diagnostics {
+          write!(f, "\n  {}", diagnostic)?;
+        }
+        Ok(())
+      },
+    }
+  }
 }
 
+impl std::error::Error for BuildError {}
+
 impl From<std::io::Error> for BuildError {
   fn from(error: std::io::Error) -> Self {
     BuildError::IOError(error)
@@ -123,14 +315,77 @@ impl From<serde_yaml::Error> for BuildError {
   }
 }
 
+/// Builds configuration from a yaml value and validates it.
+fn build_config_from_value<F>(value: serde_yaml::Value, locate: F) -> Result<Configuration, BuildError>
+  where F: Fn(&mut [Diagnostic])
+{
+  let raw: RawConfiguration = serde_yaml::from_value(value)?;
+
+  let mut config = raw.into_configuration().map_err(|mut diagnostics| {
+    locate(&mut diagnostics);
+    BuildError::ValidationError(diagnostics)
+  })?;
+
+  let mut diagnostics = config.validate();
+  if !diagnostics.is_empty() {
+    locate(&mut diagnostics);
+    return Err(BuildError::ValidationError(diagnostics));
+  }
+
+  // Scales depend on the machine, they are resolved once the application boots.
+  config.resolve_targets();
+
+  Ok(config)
+}
+
 /// Builds configuration from a yaml file.
+/// The file is merged on top of the file it `extends`, or on top of an `omnidux.yaml`
+/// in the same folder if it doesn't extend anything explicitly.
+/// Falls back to the name of the containing folder if the configuration has no name.
 pub fn build_config_from_file (file: String) -> Result<Configuration, BuildError> {
-  let content = fs::read_to_string(file)?;
-  let deserialized = serde_yaml::from_str(&content)?;
-  Ok(deserialized)
+  let layers = layering::load_layers(Path::new(&file))?;
+  let merged = layering::merge_layers(&layers);
+
+  let origins = &merged.origins;
+  let mut config = build_config_from_value(merged.value.clone(), |diagnostics| {
+    layering::locate_layered_diagnostics(&layers, origins, diagnostics);
+  })?;
+  config.origins = merged.origins;
+
+  // Manifests of repos whose sources are not available yet are skipped.
+  let project = Path::new(&file).parent().unwrap_or_else(|| Path::new(""));
+  config.load_manifests(project)?;
+  let diagnostics = config.validate_manifests();
+  if !diagnostics.is_empty() {
+    return Err(BuildError::ValidationError(diagnostics));
+  }
+
+  if config.name.is_empty() {
+    config.name = Path::new(&file)
+      .canonicalize()?
+      .parent()
+      .and_then(|parent| parent.file_name())
+      .map(|name| name.to_string_lossy().into_owned())
+      .unwrap_or_default();
+  }
+
+  Ok(config)
 }
-/// Builds configuration from a yaml string.
+
+/// Builds configuration from a yaml string of any supported layout version.
+/// The configuration is validated and any problems are reported with their location.
 pub fn build_config_from_str(content: &str) -> Result<Configuration, BuildError> {
-  let deserialized = serde_yaml::from_str(content)?;
-  Ok(deserialized)
-}
\ No newline at end of file
+  let content = layout::quote_bare_wildcards(content);
+  let value = serde_yaml::from_str(&content)?;
+
+  build_config_from_value(value, |diagnostics| {
+    diagnostic::locate_diagnostics(&content, diagnostics);
+  })
+}
+
+/// Migrates a configuration of any layout version into the current layout.
+pub fn migrate_config_str(content: &str) -> Result<String, BuildError> {
+  let config = build_config_from_str(content)?;
+  let migrated = serde_yaml::to_string(&RawConfiguration::from(config))?;
+  Ok(migrated)
+}
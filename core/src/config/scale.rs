@@ -0,0 +1,133 @@
+use super::{Schedule, ScheduleScaleValue};
+
+/// Parsed form of a `ScheduleScaleValue`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Scale {
+  /// A fixed number of threads, e.g. `2`.
+  Count (usize),
+  /// `all`, every thread selected by the schedule target.
+  All,
+  /// `50%`, a share of the threads selected by the schedule target, rounded up.
+  Percent (usize),
+  /// `cpu`, `cpu-1` or `cpu+1`, the available parallelism with an offset.
+  Cpu (isize),
+}
+
+/// Everything a scale value depends on when resolved.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ScaleContext {
+  /// Number of threads selected by the schedule target.
+  pub matching_threads: usize,
+  /// Number of threads the machine can run in parallel.
+  pub available_parallelism: usize,
+}
+
+impl ScaleContext {
+  /// Creates a context for the current machine.
+  pub fn current(matching_threads: usize) -> ScaleContext {
+    ScaleContext {
+      matching_threads: matching_threads,
+      available_parallelism: num_cpus::get(),
+    }
+  }
+}
+
+/// Concrete minimum and maximum number of threads of a schedule.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ResolvedScale {
+  pub min: usize,
+  pub max: usize,
+}
+
+impl Scale {
+  /// Whether the resolved value depends on the machine it is resolved on.
+  pub fn is_machine_dependent(&self) -> bool {
+    match self {
+      Scale::Cpu(_) => true,
+      _ => false,
+    }
+  }
+
+  /// Number of threads the scale stands for, not limited to the matching threads.
+  fn count(&self, context: &ScaleContext) -> usize {
+    match *self {
+      Scale::Count(count) => count,
+      Scale::All => context.matching_threads,
+      Scale::Percent(percent) => (context.matching_threads * percent + 99) / 100,
+      Scale::Cpu(offset) => (context.available_parallelism as isize + offset).max(0) as usize,
+    }
+  }
+
+  /// Resolves the scale into a number of threads. Counts beyond the matching threads are
+  /// rejected, machine dependent scales are capped at the matching threads.
+  pub fn resolve(&self, context: &ScaleContext) -> Result<usize, String> {
+    match *self {
+      Scale::Count(count) if count > context.matching_threads => {
+        Err(format!("scale ({}) exceeds the {} threads selected by the target", count, context.matching_threads))
+      },
+      _ => Ok(self.clamp(context)),
+    }
+  }
+
+  /// Resolves the scale into a number of threads, capped at the matching threads.
+  pub fn clamp(&self, context: &ScaleContext) -> usize {
+    self.count(context).min(context.matching_threads)
+  }
+}
+
+impl ScheduleScaleValue {
+  /// Parses the value, dynamic values support `all`, `cpu`, `cpu-<n>`, `cpu+<n>` and `<n>%`.
+  pub fn parse(&self) -> Result<Scale, String> {
+    let value = match self {
+      ScheduleScaleValue::Static(count) => return Ok(Scale::Count(*count as usize)),
+      ScheduleScaleValue::Dynamic(value) => value.trim(),
+    };
+
+    let invalid = || format!("invalid scale `{}`, expected a number, `all`, `cpu`, `cpu-<n>`, `cpu+<n>` or `<n>%`", value);
+
+    if value == "all" {
+      Ok(Scale::All)
+    } else if value == "cpu" {
+      Ok(Scale::Cpu(0))
+    } else if value.starts_with("cpu") {
+      let offset = &value[3..];
+      let sign = match offset.chars().next() {
+        Some('-') => -1,
+        Some('+') => 1,
+        _ => return Err(invalid()),
+      };
+      offset[1..].parse::<isize>().map(|n| Scale::Cpu(sign * n)).map_err(|_| invalid())
+    } else if value.ends_with('%') {
+      match value[..value.len() - 1].parse::<usize>() {
+        Ok(percent) if percent <= 100 => Ok(Scale::Percent(percent)),
+        _ => Err(invalid()),
+      }
+    } else {
+      value.parse::<usize>().map(Scale::Count).map_err(|_| invalid())
+    }
+  }
+
+  /// Parses and resolves the value into a number of threads.
+  pub fn resolve(&self, context: &ScaleContext) -> Result<usize, String> {
+    self.parse().and_then(|scale| scale.resolve(context))
+  }
+}
+
+impl Schedule {
+  /// Resolves `min` and `max` for the machine the application boots on, based on the
+  /// resolved target threads. Never fails: values are capped at the target threads, a
+  /// `min` above `max` is lowered to `max` and a schedule with targets keeps at least one.
+  pub fn resolve_scale(&self, available_parallelism: usize) -> ResolvedScale {
+    let context = ScaleContext {
+      matching_threads: self.target_threads.len(),
+      available_parallelism: available_parallelism,
+    };
+    let resolve = |value: &ScheduleScaleValue, fallback: usize| {
+      value.parse().map(|scale| scale.clamp(&context)).unwrap_or(fallback)
+    };
+
+    let max = resolve(&self.max, context.matching_threads).max(context.matching_threads.min(1));
+    let min = resolve(&self.min, 1).min(max);
+    ResolvedScale { min: min, max: max }
+  }
+}
@@ -0,0 +1,34 @@
+use schemars::schema::{InstanceType, RootSchema, Schema, SchemaObject};
+use schemars::schema_for;
+
+use super::RawConfiguration;
+use super::layering::EXTENDS_KEY;
+
+/// Generates the JSON Schema of the configuration file, covering every supported layout.
+pub fn json_schema() -> RootSchema {
+  let mut schema = schema_for!(RawConfiguration);
+  schema.schema.metadata().title = Some("omnidux configuration".to_string());
+
+  // Layered files only hold a part of the configuration, e.g. the shared `omnidux.yaml`
+  // has no `target` and platform files don't repeat its `repos`, so no top level field
+  // is required. Missing fields are reported once the layers are merged.
+  schema.schema.object().required.clear();
+  if let Some(Schema::Object(application)) = schema.definitions.get_mut("Application") {
+    application.object().required.clear();
+  }
+
+  // `extends` is consumed while layering and never reaches the deserialized configuration.
+  let mut extends = SchemaObject {
+    instance_type: Some(InstanceType::String.into()),
+    ..Default::default()
+  };
+  extends.metadata().description = Some("Path of a configuration this file is merged on top of.".to_string());
+  schema.schema.object().properties.insert(EXTENDS_KEY.to_string(), Schema::Object(extends));
+
+  schema
+}
+
+/// Generates the JSON Schema of the configuration file as pretty printed json.
+pub fn json_schema_string() -> String {
+  serde_json::to_string_pretty(&json_schema()).unwrap()
+}
@@ -0,0 +1,159 @@
+use super::{Thread, ThreadType, ScheduleTarget};
+
+/// A single selector of a schedule target expression.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Selector {
+  /// `*`, selects every thread.
+  All,
+  /// `worker*`, selects threads by name, supports `*` and `?` wildcards.
+  Name (String),
+  /// `tag:<glob>`, selects threads that carry a matching tag.
+  Tag (String),
+  /// `type:<thread|main|webworker>`, selects threads by their type.
+  Type (ThreadType),
+}
+
+/// A selector that either adds or removes threads from the selection.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Term {
+  pub exclude: bool,
+  pub selector: Selector,
+}
+
+/// Parsed schedule target, e.g. `* !main` or `tag:background !worker2`.
+///
+/// All threads matched by any including term are selected, minus all threads matched
+/// by any excluding term. An expression consisting only of exclusions starts from all threads.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TargetExpression {
+  pub terms: Vec<Term>,
+}
+
+/// Matches a text against a glob pattern with `*` and `?` wildcards.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+  let pattern: Vec<char> = pattern.chars().collect();
+  let text: Vec<char> = text.chars().collect();
+
+  fn matches(pattern: &[char], text: &[char]) -> bool {
+    match pattern.first() {
+      None => text.is_empty(),
+      Some('*') => (0..=text.len()).any(|i| matches(&pattern[1..], &text[i..])),
+      Some('?') => !text.is_empty() && matches(&pattern[1..], &text[1..]),
+      Some(c) => text.first() == Some(c) && matches(&pattern[1..], &text[1..]),
+    }
+  }
+
+  matches(&pattern, &text)
+}
+
+fn is_glob(pattern: &str) -> bool {
+  pattern.contains(&['*', '?'][..])
+}
+
+fn parse_thread_type(name: &str) -> Result<ThreadType, String> {
+  match name {
+    "thread" => Ok(ThreadType::Thread),
+    "main" => Ok(ThreadType::Main),
+    "webworker" => Ok(ThreadType::WebWorker),
+    _ => Err(format!("unknown thread type `{}`, expected one of thread, main, webworker", name)),
+  }
+}
+
+impl Selector {
+  fn parse(term: &str) -> Result<Selector, String> {
+    if term == "*" {
+      return Ok(Selector::All);
+    }
+
+    match term.find(':') {
+      Some(index) => {
+        let (kind, value) = (&term[..index], &term[index + 1..]);
+        if value.is_empty() {
+          return Err(format!("selector `{}` is missing a value", term));
+        }
+
+        match kind {
+          "tag" => Ok(Selector::Tag(value.to_string())),
+          "type" => parse_thread_type(value).map(Selector::Type),
+          _ => Err(format!("unknown selector `{}`, expected `tag:` or `type:`", kind)),
+        }
+      },
+      None => Ok(Selector::Name(term.to_string())),
+    }
+  }
+
+  /// Whether the thread is matched by this selector.
+  pub fn matches(&self, thread: &Thread) -> bool {
+    match self {
+      Selector::All => true,
+      Selector::Name(pattern) => glob_match(pattern, &thread.name),
+      Selector::Tag(pattern) => thread.tags.iter().any(|tag| glob_match(pattern, tag)),
+      Selector::Type(thread_type) => thread.thread_type == *thread_type,
+    }
+  }
+}
+
+impl TargetExpression {
+  /// Parses a whitespace separated list of terms, terms prefixed by `!` are excluded.
+  pub fn parse(expression: &str) -> Result<TargetExpression, String> {
+    let mut terms = Vec::new();
+
+    for term in expression.split_whitespace() {
+      let (exclude, term) = if term.starts_with('!') { (true, &term[1..]) } else { (false, term) };
+      if term.is_empty() {
+        return Err("`!` must be followed by a selector".to_string());
+      }
+
+      terms.push(Term {
+        exclude: exclude,
+        selector: Selector::parse(term)?,
+      });
+    }
+
+    if terms.is_empty() {
+      return Err("target expression is empty".to_string());
+    }
+
+    Ok(TargetExpression { terms: terms })
+  }
+
+  /// Returns the indices of all selected threads in ascending order.
+  pub fn select(&self, threads: &[Thread]) -> Vec<usize> {
+    let only_exclusions = self.terms.iter().all(|term| term.exclude);
+
+    threads.iter()
+      .enumerate()
+      .filter(|(_, thread)| {
+        let included = only_exclusions || self.terms.iter().any(|term| !term.exclude && term.selector.matches(thread));
+        included && !self.terms.iter().any(|term| term.exclude && term.selector.matches(thread))
+      })
+      .map(|(i, _)| i)
+      .collect()
+  }
+
+  /// Returns all plain names (no wildcards) that don't refer to any thread.
+  pub fn unknown_names(&self, threads: &[Thread]) -> Vec<String> {
+    self.terms.iter()
+      .filter_map(|term| match &term.selector {
+        Selector::Name(name) if !is_glob(name) => Some(name.clone()),
+        _ => None,
+      })
+      .filter(|name| !threads.iter().any(|thread| thread.name == *name))
+      .collect()
+  }
+}
+
+impl ScheduleTarget {
+  /// Parses the target into a single expression, lists are combined into one expression.
+  pub fn parse(&self) -> Result<TargetExpression, String> {
+    match self {
+      ScheduleTarget::Expression(expression) => TargetExpression::parse(expression),
+      ScheduleTarget::Multiple(expressions) => TargetExpression::parse(&expressions.join(" ")),
+    }
+  }
+
+  /// Resolves the target into the indices of the selected threads.
+  pub fn resolve(&self, threads: &[Thread]) -> Result<Vec<usize>, String> {
+    self.parse().map(|expression| expression.select(threads))
+  }
+}
@@ -0,0 +1,175 @@
+use std::collections::HashSet;
+
+use super::{Configuration, Scope, ScheduleTarget, RepoType};
+use super::diagnostic::Diagnostic;
+use super::scale::ScaleContext;
+use super::selector::TargetExpression;
+use super::LEGACY_VERSION;
+
+/// Prefix of repos that are shipped with omnidux itself.
+pub const SYS_REPO_PREFIX: &str = "sys/";
+
+impl Configuration {
+  /// Checks all parts of the configuration against each other and returns
+  /// every problem that was found. An empty list means the configuration is valid.
+  pub fn validate(&self) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+
+    self.validate_repos(&mut diagnostics);
+    self.validate_threads(&mut diagnostics);
+    self.validate_scopes(&self.scopes, "scopes".to_string(), &mut diagnostics);
+    self.validate_setup(&mut diagnostics);
+
+    diagnostics
+  }
+
+  /// Whether the given repo name is either configured or a built-in sys repo.
+  pub fn has_repo(&self, name: &str) -> bool {
+    name.starts_with(SYS_REPO_PREFIX) || self.repos.iter().any(|repo| repo.name() == name)
+  }
+
+  /// Whether a thread with the given name is configured.
+  pub fn has_thread(&self, name: &str) -> bool {
+    self.threads.iter().any(|thread| thread.name == name)
+  }
+
+  /// Path of the thread list inside of the source layout.
+  fn threads_path(&self) -> &'static str {
+    if self.version == LEGACY_VERSION { "threads" } else { "application.threads" }
+  }
+
+  /// Path of the schedule list inside of the source layout.
+  pub(crate) fn setup_path(&self) -> &'static str {
+    if self.version == LEGACY_VERSION { "setup" } else { "application.repos" }
+  }
+
+  fn validate_repos(&self, diagnostics: &mut Vec<Diagnostic>) {
+    let mut names = HashSet::new();
+    for (i, repo) in self.repos.iter().enumerate() {
+      match &repo.repo_type {
+        RepoType::Local if repo.source.is_empty() => {
+          diagnostics.push(Diagnostic::new(
+            format!("repos[{}]", i),
+            "local repos require a `source`".to_string(),
+          ));
+        },
+        RepoType::Git { rev, branch, tag, .. } => {
+          if [rev, branch, tag].iter().filter(|reference| reference.is_some()).count() > 1 {
+            diagnostics.push(Diagnostic::new(
+              format!("repos[{}]", i),
+              "git repos accept only one of `rev`, `branch` and `tag`".to_string(),
+            ));
+          }
+        },
+        _ => {},
+      }
+
+      if !names.insert(repo.name()) {
+        diagnostics.push(Diagnostic::new(
+          format!("repos[{}].source", i),
+          format!("repo `{}` is defined more than once", repo.name()),
+        ));
+      }
+    }
+  }
+
+  fn validate_threads(&self, diagnostics: &mut Vec<Diagnostic>) {
+    let mut names = HashSet::new();
+    for (i, thread) in self.threads.iter().enumerate() {
+      if !names.insert(thread.name.as_str()) {
+        diagnostics.push(Diagnostic::new(
+          format!("{}[{}].name", self.threads_path(), i),
+          format!("thread `{}` is defined more than once", thread.name),
+        ));
+      }
+    }
+  }
+
+  fn validate_scopes(&self, scopes: &[Scope], path: String, diagnostics: &mut Vec<Diagnostic>) {
+    for (i, scope) in scopes.iter().enumerate() {
+      let scope_path = format!("{}[{}]", path, i);
+      if !self.has_repo(&scope.repo) {
+        diagnostics.push(Diagnostic::new(
+          format!("{}.repo", scope_path),
+          format!("scope references unknown repo `{}`", scope.repo),
+        ));
+      }
+
+      if let Some(children) = &scope.scopes {
+        self.validate_scopes(children, format!("{}.scopes", scope_path), diagnostics);
+      }
+    }
+  }
+
+  fn validate_setup(&self, diagnostics: &mut Vec<Diagnostic>) {
+    for (i, schedule) in self.setup.iter().enumerate() {
+      if !self.has_repo(&schedule.repo) {
+        diagnostics.push(Diagnostic::new(
+          format!("{}[{}].repo", self.setup_path(), i),
+          format!("setup references unknown repo `{}`", schedule.repo),
+        ));
+      }
+
+      let expressions = match &schedule.target {
+        ScheduleTarget::Expression(expression) => vec![(format!("{}[{}].target", self.setup_path(), i), expression)],
+        ScheduleTarget::Multiple(expressions) => expressions.iter()
+          .enumerate()
+          .map(|(j, expression)| (format!("{}[{}].target[{}]", self.setup_path(), i, j), expression))
+          .collect(),
+      };
+
+      let mut is_valid_target = true;
+      for (path, expression) in expressions {
+        match TargetExpression::parse(expression) {
+          Ok(parsed) => {
+            for name in parsed.unknown_names(&self.threads) {
+              is_valid_target = false;
+              diagnostics.push(Diagnostic::new(path.clone(), format!("schedule targets unknown thread `{}`", name)));
+            }
+          },
+          Err(message) => {
+            is_valid_target = false;
+            diagnostics.push(Diagnostic::new(path, message));
+          },
+        }
+      }
+
+      if is_valid_target && schedule.target.resolve(&self.threads).map(|targets| targets.is_empty()).unwrap_or(false) {
+        diagnostics.push(Diagnostic::new(
+          format!("{}[{}].target", self.setup_path(), i),
+          "schedule target does not select any thread".to_string(),
+        ));
+      }
+
+      let min = schedule.min.parse();
+      let max = schedule.max.parse();
+      for (key, scale) in [("min", &min), ("max", &max)].iter() {
+        if let Err(message) = scale {
+          diagnostics.push(Diagnostic::new(format!("{}[{}].{}", self.setup_path(), i, key), message.clone()));
+        }
+      }
+
+      if let (Ok(min), Ok(max), Ok(targets)) = (min, max, schedule.target.resolve(&self.threads)) {
+        if !targets.is_empty() {
+          let context = ScaleContext { matching_threads: targets.len(), available_parallelism: 1 };
+          let (min_count, max_count) = (min.resolve(&context), max.resolve(&context));
+          for (key, count) in [("min", &min_count), ("max", &max_count)].iter() {
+            if let Err(message) = count {
+              diagnostics.push(Diagnostic::new(format!("{}[{}].{}", self.setup_path(), i, key), message.clone()));
+            }
+          }
+
+          // Machine dependent scales can only be compared once they are resolved at boot.
+          if let (Ok(min_count), Ok(max_count)) = (min_count, max_count) {
+            if !min.is_machine_dependent() && !max.is_machine_dependent() && min_count > max_count {
+              diagnostics.push(Diagnostic::new(
+                format!("{}[{}].min", self.setup_path(), i),
+                format!("min ({}) must not be greater than max ({})", min_count, max_count),
+              ));
+            }
+          }
+        }
+      }
+    }
+  }
+}
@@ -0,0 +1,321 @@
+use std::io::{self, BufRead, Write};
+use std::sync::{Arc, Mutex};
+
+use crate::config::{Configuration, Schedule};
+use crate::repo::Repository;
+use crate::scheduler::context::Context;
+use crate::task::{Task, TaskPayload};
+use crate::threads::Thread;
+
+/// Creates a repository from its schedule configuration, `Repository::new` of every
+/// `create_repo!` fits this signature.
+pub type RepoFactory = fn(Schedule, &mut usize) -> Arc<dyn Repository + Send + Sync>;
+
+/// Registers a repo crate under its configured name, for `Debugger::register`.
+#[macro_export]
+macro_rules! debug_repo {
+  ($name:expr, $repo:ident) => {
+    (
+      $name,
+      (|schedule, counter| std::sync::Arc::new($repo::Repository::new(schedule, counter)))
+        as omnidux_core::debug::RepoFactory,
+    )
+  };
+}
+
+const HELP: &str = "\
+Commands:
+  repos                                 Lists repos, their task uuids and capsules
+  threads                               Lists threads
+  schedule <repo> <task> [payload] [@thread]
+                                        Schedules a task on the first eligible thread of its
+                                        repo, or on the given one. Only tasks whose input is
+                                        `String`, or `()` without payload, can be scheduled
+  get <repo> <capsule> <key>            Reads a capsule key
+  set <repo> <capsule> <key> <value>    Writes a capsule key
+  log                                   Shows which thread ran which task
+  help                                  Shows this help
+  quit                                  Exits the debugger";
+
+/// A handled task, recorded by the debugger.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Execution {
+  pub thread: usize,
+  pub task: usize,
+}
+
+/// Boots repos and threads of a configuration in-process and inspects them.
+pub struct Debugger {
+  config: Configuration,
+  factories: Vec<(&'static str, RepoFactory)>,
+  threads: Vec<Thread>,
+  context: Option<Context>,
+  log: Arc<Mutex<Vec<Execution>>>,
+  log_cursor: usize,
+}
+
+impl Debugger {
+  pub fn new(config: &Configuration) -> Self {
+    Debugger {
+      config: config.clone(),
+      factories: Vec::new(),
+      threads: Vec::new(),
+      context: None,
+      log: Arc::new(Mutex::new(Vec::new())),
+      log_cursor: 0,
+    }
+  }
+
+  /// Registers the repos that are linked into the debugger, see `debug_repo!`.
+  pub fn register(mut self, factories: Vec<(&'static str, RepoFactory)>) -> Self {
+    self.factories.extend(factories);
+    self
+  }
+
+  /// Instantiates one repo per setup entry and spawns all threads.
+  /// Returns a line per setup entry that could not be booted.
+  pub fn boot(&mut self) -> Vec<String> {
+    let mut warnings = Vec::new();
+    let mut counter = 0usize;
+    let mut repos = Vec::new();
+
+    for schedule in &self.config.setup {
+      match self.factories.iter().find(|(name, _)| *name == schedule.repo) {
+        Some((_, factory)) => repos.push(factory(schedule.clone(), &mut counter)),
+        None => warnings.push(format!("Repo `{}` is not linked into the debugger, skipping it.", schedule.repo)),
+      }
+    }
+
+    let mut senders = Vec::new();
+    for (i, thread) in self.config.threads.iter().enumerate() {
+      let thread = Thread::new(i, thread.clone());
+      senders.push(thread.create_sender());
+      self.threads.push(thread);
+    }
+
+    let mut context = Context::new(repos, senders);
+    let log = self.log.clone();
+    context.set_execution_listener(Arc::new(move |thread, task: &Task| {
+      log.lock().unwrap().push(Execution { thread: thread, task: task.uuid });
+    }));
+
+    for thread in &mut self.threads {
+      thread.spawn(&context);
+    }
+    context.spawn_timers();
+
+    self.context = Some(context);
+    warnings
+  }
+
+  /// Handles tasks of threads that are not spawned, like the main thread, which
+  /// runs inside of the debugger prompt.
+  pub fn poll(&mut self) -> usize {
+    let context = match &self.context {
+      Some(context) => context,
+      None => return 0,
+    };
+
+    self.threads.iter_mut().map(|thread| thread.poll(context)).sum()
+  }
+
+  fn find_repo(&self, name: &str) -> Result<&Arc<dyn Repository + Send + Sync>, String> {
+    self.context.as_ref()
+      .ok_or_else(|| "Debugger has not been booted.".to_string())?
+      .get_repos()
+      .iter()
+      .find(|repo| repo.get_schedule_config().repo == name)
+      .ok_or_else(|| format!("Unknown repo `{}`.", name))
+  }
+
+  fn describe_task(&self, uuid: usize) -> String {
+    self.context.as_ref()
+      .and_then(|context| context.get_repos().iter().find(|repo| repo.has_ownership(uuid)))
+      .and_then(|repo| {
+        let name = repo.get_schedule_config().repo;
+        repo.get_tasks().into_iter().find(|(task, _)| *task == uuid).map(|(_, task)| format!("{}::{}", name, task))
+      })
+      .unwrap_or_else(|| format!("#{}", uuid))
+  }
+
+  fn list_repos(&self) -> Result<String, String> {
+    let context = self.context.as_ref().ok_or_else(|| "Debugger has not been booted.".to_string())?;
+
+    let mut output = Vec::new();
+    for repo in context.get_repos() {
+      output.push(repo.get_schedule_config().repo);
+      for (uuid, task) in repo.get_tasks() {
+        output.push(format!("  task {:>4}  {}", uuid, task));
+      }
+      for capsule in repo.get_capsule_names() {
+        output.push(format!("  capsule    {}", capsule));
+      }
+    }
+
+    Ok(output.join("\n"))
+  }
+
+  fn list_threads(&self) -> String {
+    self.threads.iter()
+      .map(|thread| {
+        let config = thread.get_config();
+        format!("{:>4}  {}  {:?}  {:?}", thread.get_uuid(), config.name, config.thread_type, config.driver)
+      })
+      .collect::<Vec<_>>()
+      .join("\n")
+  }
+
+  fn schedule(&self, args: &[&str]) -> Result<String, String> {
+    let (repo_name, task_name) = match args {
+      [repo, task, ..] => (*repo, *task),
+      _ => return Err("Usage: schedule <repo> <task> [payload] [@thread]".to_string()),
+    };
+
+    let repo = self.find_repo(repo_name)?;
+    let (uuid, _) = repo.get_tasks().into_iter()
+      .find(|(_, name)| *name == task_name)
+      .ok_or_else(|| format!("Repo `{}` has no task `{}`.", repo_name, task_name))?;
+
+    let mut payload = Vec::new();
+    let mut targets = None;
+    for arg in &args[2..] {
+      if arg.starts_with('@') {
+        let name = &arg[1..];
+        let thread = self.threads.iter()
+          .find(|thread| thread.get_config().name == name)
+          .ok_or_else(|| format!("Unknown thread `{}`.", name))?;
+        targets = Some(vec![thread.get_uuid()]);
+      } else {
+        payload.push(*arg);
+      }
+    }
+
+    // Words are passed as a `String` input, tasks without input receive `()`.
+    let payload: TaskPayload = if payload.is_empty() {
+      Arc::new(())
+    } else {
+      Arc::new(payload.join(" "))
+    };
+    // Without a thread the task runs once, on the first thread its repo may use.
+    let context = self.context.as_ref().unwrap();
+    let targets = match targets {
+      Some(targets) => targets,
+      None => context.get_eligible_threads(uuid).first()
+        .map(|thread| vec![thread.uuid])
+        .ok_or_else(|| format!("Repo `{}` has no thread to schedule on.", repo_name))?,
+    };
+    let task = Task {
+      execution_targets: None,
+      uuid: uuid,
+      payload: payload,
+      key: None,
+      priority: None,
+      completion: None,
+    };
+
+    context.schedule_on(task, targets.clone());
+    Ok(format!("Scheduled {} on threads {:?}.", self.describe_task(uuid), targets))
+  }
+
+  fn capsule(&self, args: &[&str]) -> Result<String, String> {
+    match args {
+      ["get", repo, capsule, key] => {
+        self.find_repo(repo)?
+          .get_capsule(capsule)
+          .ok_or_else(|| format!("Repo `{}` has no capsule `{}`.", repo, capsule))?
+          .debug_get(key)
+      },
+      ["set", repo, capsule, key, value] => {
+        self.find_repo(repo)?
+          .get_capsule(capsule)
+          .ok_or_else(|| format!("Repo `{}` has no capsule `{}`.", repo, capsule))?
+          .debug_set(key, value)
+          .map(|_| format!("Set {}.{}[{}].", repo, capsule, key))
+      },
+      ["get", ..] => Err("Usage: get <repo> <capsule> <key>".to_string()),
+      _ => Err("Usage: set <repo> <capsule> <key> <value>".to_string()),
+    }
+  }
+
+  /// Returns the executions that have been recorded since the last call.
+  pub fn take_executions(&mut self) -> Vec<Execution> {
+    let log = self.log.lock().unwrap();
+    let executions = log[self.log_cursor..].to_vec();
+    self.log_cursor = log.len();
+    executions
+  }
+
+  fn show_log(&mut self) -> String {
+    let executions = self.take_executions();
+    if executions.is_empty() {
+      return "No tasks ran since the last check.".to_string();
+    }
+
+    executions.iter()
+      .map(|execution| {
+        let thread = self.config.threads.get(execution.thread).map(|t| t.name.as_str()).unwrap_or("?");
+        format!("[{}] {}", thread, self.describe_task(execution.task))
+      })
+      .collect::<Vec<_>>()
+      .join("\n")
+  }
+
+  /// Executes a single command of the prompt and returns its output.
+  pub fn execute(&mut self, line: &str) -> Result<String, String> {
+    let args: Vec<&str> = line.split_whitespace().collect();
+
+    let result = match args.first() {
+      None => Ok("".to_string()),
+      Some(&"help") => Ok(HELP.to_string()),
+      Some(&"repos") => self.list_repos(),
+      Some(&"threads") => Ok(self.list_threads()),
+      Some(&"schedule") => self.schedule(&args[1..]),
+      Some(&"get") | Some(&"set") => self.capsule(&args),
+      Some(&"log") => {
+        self.poll();
+        Ok(self.show_log())
+      },
+      Some(command) => Err(format!("Unknown command `{}`, see `help`.", command)),
+    };
+
+    self.poll();
+    result
+  }
+
+  /// Runs the interactive prompt on stdin until `quit` or end of input. Fails without
+  /// starting a session if the configuration has repos but none are linked in.
+  pub fn run(&mut self) -> Result<(), String> {
+    if self.factories.is_empty() && !self.config.setup.is_empty() {
+      return Err("No repos are linked into the debugger, start it through `omnidux debug`.".to_string());
+    }
+
+    for warning in self.boot() {
+      println!("{}", warning);
+    }
+    println!("omnidux debugger for {}, type `help` for a list of commands.", self.config.name);
+
+    let stdin = io::stdin();
+    loop {
+      print!("> ");
+      io::stdout().flush().unwrap();
+
+      let mut line = String::new();
+      if stdin.lock().read_line(&mut line).unwrap_or(0) == 0 || line.trim() == "quit" {
+        break;
+      }
+
+      match self.execute(&line) {
+        Ok(output) => if !output.is_empty() { println!("{}", output) },
+        Err(error) => println!("error: {}", error),
+      }
+    }
+
+    Ok(())
+  }
+}
+
+/// Runs the debugger for a configuration with the given repos linked in, called by the
+/// `examples/debug.rs` entry point of the generated crate.
+pub fn run_debugger(config: &Configuration, factories: Vec<(&'static str, RepoFactory)>) -> Result<(), String> {
+  Debugger::new(config).register(factories).run()
+}
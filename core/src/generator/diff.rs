@@ -0,0 +1,110 @@
+/// Lines of context around every change.
+const CONTEXT: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Line {
+  Equal(usize, usize),
+  Delete(usize),
+  Insert(usize),
+}
+
+/// Aligns both texts line by line along their longest common subsequence.
+fn align(old: &[&str], new: &[&str]) -> Vec<Line> {
+  let mut lengths = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+  for i in (0..old.len()).rev() {
+    for j in (0..new.len()).rev() {
+      lengths[i][j] = if old[i] == new[j] {
+        lengths[i + 1][j + 1] + 1
+      } else {
+        lengths[i + 1][j].max(lengths[i][j + 1])
+      };
+    }
+  }
+
+  let mut lines = Vec::new();
+  let (mut i, mut j) = (0, 0);
+  while i < old.len() || j < new.len() {
+    if i < old.len() && j < new.len() && old[i] == new[j] {
+      lines.push(Line::Equal(i, j));
+      i += 1;
+      j += 1;
+    } else if i < old.len() && (j == new.len() || lengths[i + 1][j] >= lengths[i][j + 1]) {
+      lines.push(Line::Delete(i));
+      i += 1;
+    } else {
+      lines.push(Line::Insert(j));
+      j += 1;
+    }
+  }
+
+  lines
+}
+
+/// Formats the start and length of a hunk range, empty ranges point at the line before.
+fn hunk_range(start: usize, length: usize) -> String {
+  match length {
+    0 => format!("{},0", start),
+    1 => format!("{}", start + 1),
+    _ => format!("{},{}", start + 1, length),
+  }
+}
+
+/// Creates a unified diff between two texts, empty if they are equal.
+/// A missing old text is shown as a new file.
+pub fn unified_diff(old: Option<&str>, new: &str, name: &str) -> String {
+  let old_lines: Vec<&str> = old.map(|old| old.lines().collect()).unwrap_or_default();
+  let new_lines: Vec<&str> = new.lines().collect();
+  let lines = align(&old_lines, &new_lines);
+
+  let changes: Vec<usize> = lines.iter()
+    .enumerate()
+    .filter(|(_, line)| match line { Line::Equal(_, _) => false, _ => true })
+    .map(|(index, _)| index)
+    .collect();
+  if changes.is_empty() {
+    return String::new();
+  }
+
+  let mut output = match old {
+    Some(_) => format!("--- a/{n}\n+++ b/{n}\n", n = name),
+    None => format!("--- /dev/null\n+++ b/{n}\n", n = name),
+  };
+
+  // Group changes whose context overlaps into one hunk.
+  let mut hunks: Vec<(usize, usize)> = Vec::new();
+  for &change in &changes {
+    let start = change.saturating_sub(CONTEXT);
+    let end = (change + CONTEXT + 1).min(lines.len());
+    match hunks.last_mut() {
+      Some(last) if start <= last.1 => last.1 = end,
+      _ => hunks.push((start, end)),
+    }
+  }
+
+  for (start, end) in hunks {
+    let hunk = &lines[start..end];
+
+    // Position of the hunk within both texts.
+    let (mut old_start, mut new_start) = (0, 0);
+    for line in &lines[..start] {
+      match line {
+        Line::Equal(_, _) => { old_start += 1; new_start += 1; },
+        Line::Delete(_) => old_start += 1,
+        Line::Insert(_) => new_start += 1,
+      }
+    }
+    let old_length = hunk.iter().filter(|line| match line { Line::Insert(_) => false, _ => true }).count();
+    let new_length = hunk.iter().filter(|line| match line { Line::Delete(_) => false, _ => true }).count();
+
+    output.push_str(&format!("@@ -{} +{} @@\n", hunk_range(old_start, old_length), hunk_range(new_start, new_length)));
+    for line in hunk {
+      match *line {
+        Line::Equal(i, _) => output.push_str(&format!(" {}\n", old_lines[i])),
+        Line::Delete(i) => output.push_str(&format!("-{}\n", old_lines[i])),
+        Line::Insert(j) => output.push_str(&format!("+{}\n", new_lines[j])),
+      }
+    }
+  }
+
+  output
+}
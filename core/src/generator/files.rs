@@ -0,0 +1,172 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use super::diff::unified_diff;
+
+/// Marks the start of a region inside of a generated file that is owned by the developer,
+/// followed by the name of the region, e.g. `// omnidux:begin-user imports`.
+pub const USER_REGION_BEGIN: &str = "omnidux:begin-user";
+/// Marks the end of a region owned by the developer, followed by the name of the region.
+pub const USER_REGION_END: &str = "omnidux:end-user";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChangeKind {
+  Create,
+  Update,
+  Delete,
+  Unchanged,
+}
+
+/// A generated file together with the content currently on disk.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileChange {
+  pub path: PathBuf,
+  /// Content on disk, `None` if the file doesn't exist yet.
+  pub previous: Option<String>,
+  pub content: String,
+  /// Whether the file is removed instead of written, `content` is empty then.
+  pub deleted: bool,
+}
+
+impl FileChange {
+  pub fn kind(&self) -> ChangeKind {
+    match &self.previous {
+      _ if self.deleted => ChangeKind::Delete,
+      None => ChangeKind::Create,
+      Some(previous) if *previous != self.content => ChangeKind::Update,
+      Some(_) => ChangeKind::Unchanged,
+    }
+  }
+
+  /// Unified diff of the change, empty if the file is unchanged.
+  pub fn diff(&self) -> String {
+    let name = self.path.to_string_lossy();
+    let diff = unified_diff(self.previous.as_ref().map(|previous| previous.as_str()), &self.content, &name);
+    if self.deleted {
+      diff.replacen(&format!("+++ b/{}\n", name), "+++ /dev/null\n", 1)
+    } else {
+      diff
+    }
+  }
+
+  /// Writes or removes the file if it changed, returns whether it was touched.
+  pub fn apply(&self) -> io::Result<bool> {
+    match self.kind() {
+      ChangeKind::Unchanged => return Ok(false),
+      ChangeKind::Delete => {
+        fs::remove_file(&self.path)?;
+        return Ok(true);
+      },
+      _ => {},
+    }
+
+    if let Some(parent) = self.path.parent() {
+      fs::create_dir_all(parent)?;
+    }
+    fs::write(&self.path, &self.content)?;
+    Ok(true)
+  }
+}
+
+/// Name of the user region a line begins or ends.
+fn region_name<'a>(line: &'a str, marker: &str) -> Option<&'a str> {
+  let start = line.find(marker)? + marker.len();
+  Some(line[start..].trim())
+}
+
+/// Collects the lines inside of every user region of a file.
+fn user_regions(content: &str) -> HashMap<String, Vec<&str>> {
+  let mut regions = HashMap::new();
+  let mut current: Option<(&str, Vec<&str>)> = None;
+
+  for line in content.lines() {
+    if let Some(name) = region_name(line, USER_REGION_END) {
+      if let Some((region, lines)) = current.take() {
+        if region == name {
+          regions.insert(region.to_string(), lines);
+        }
+      }
+    } else if let Some((_, lines)) = current.as_mut() {
+      lines.push(line);
+    } else if let Some(name) = region_name(line, USER_REGION_BEGIN) {
+      current = Some((name, Vec::new()));
+    }
+  }
+
+  regions
+}
+
+/// Carries the user regions of the existing file over into the generated content.
+pub fn merge_user_regions(existing: &str, generated: &str) -> String {
+  let regions = user_regions(existing);
+  let mut merged = Vec::new();
+  let mut skipping = None;
+
+  for line in generated.lines() {
+    if let Some(name) = skipping {
+      if region_name(line, USER_REGION_END) == Some(name) {
+        merged.push(line.to_string());
+        skipping = None;
+      }
+      continue;
+    }
+
+    merged.push(line.to_string());
+    if let Some(name) = region_name(line, USER_REGION_BEGIN) {
+      if let Some(lines) = regions.get(name) {
+        merged.extend(lines.iter().map(|line| line.to_string()));
+        skipping = Some(name);
+      }
+    }
+  }
+
+  let mut merged = merged.join("\n");
+  if generated.ends_with('\n') {
+    merged.push('\n');
+  }
+  merged
+}
+
+/// Content of a file on disk, `None` if it doesn't exist.
+fn read_existing(path: &Path) -> io::Result<Option<String>> {
+  match fs::read_to_string(path) {
+    Ok(previous) => Ok(Some(previous)),
+    Err(ref error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+    Err(error) => Err(error),
+  }
+}
+
+/// Compares a generated file with the one on disk, keeping its user regions.
+pub fn plan_file(path: PathBuf, generated: String) -> io::Result<FileChange> {
+  let previous = read_existing(&path)?;
+
+  let content = match &previous {
+    Some(previous) => merge_user_regions(previous, &generated),
+    None => generated,
+  };
+
+  Ok(FileChange {
+    path: path,
+    previous: previous,
+    content: content,
+    deleted: false,
+  })
+}
+
+/// Plans the removal of a file that is no longer generated, `None` if it doesn't exist
+/// or doesn't start with `header`, i.e. was not generated by omnidux.
+pub fn plan_deletion(path: PathBuf, header: &str) -> io::Result<Option<FileChange>> {
+  let previous = match read_existing(&path)? {
+    Some(previous) if previous.starts_with(header) => previous,
+    _ => return Ok(None),
+  };
+
+  Ok(Some(FileChange {
+    path: path,
+    previous: Some(previous),
+    content: String::new(),
+    deleted: true,
+  }))
+}
@@ -1 +1,6 @@
-pub mod rust;
\ No newline at end of file
+pub mod diff;
+pub mod files;
+pub mod platform;
+pub mod rust;
+pub mod scaffold;
+pub mod template;
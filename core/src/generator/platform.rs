@@ -0,0 +1,204 @@
+use std::path::PathBuf;
+
+use serde::Serialize;
+
+use crate::config::Platform;
+
+use super::template::{render_template, render_template_with_partials};
+
+const ENTRY_PRELUDE_TEMPLATE: &str = include_str!("entry_prelude.rs.hbs");
+const ENTRY_SETUP_TEMPLATE: &str = include_str!("entry_setup.rs.hbs");
+const ENTRY_BOOTSTRAP_TEMPLATE: &str = include_str!("entry_bootstrap.rs.hbs");
+const TEST_MAIN_TEMPLATE: &str = include_str!("test_main.rs.hbs");
+const IOS_LIB_TEMPLATE: &str = include_str!("ios_lib.rs.hbs");
+const IOS_HEADER_TEMPLATE: &str = include_str!("ios_header.h.hbs");
+const WEB_LIB_TEMPLATE: &str = include_str!("web_lib.rs.hbs");
+const WEB_WORKERS_TEMPLATE: &str = include_str!("web_workers.js.hbs");
+const DEBUG_MAIN_TEMPLATE: &str = include_str!("debug_main.rs.hbs");
+
+/// Entry point of the debugger inside of the generated crate, run by `omnidux debug`.
+pub const DEBUG_ENTRY: &str = "examples/debug.rs";
+
+/// A repo instantiated by the entry point of the generated crate.
+#[derive(Debug, Clone, Serialize)]
+pub struct EntryRepo {
+  pub repo: String,
+  pub crate_name: String,
+  /// Index of the setup entry the repo is created from.
+  pub index: usize,
+}
+
+/// Everything the entry point needs to boot the application.
+#[derive(Debug, Clone, Serialize)]
+pub struct EntryData {
+  /// Name of the generated crate.
+  pub name: String,
+  /// Configuration snapshot inside of the generated crate.
+  pub config_file: &'static str,
+  pub crates: Vec<String>,
+  pub repos: Vec<EntryRepo>,
+}
+
+/// A dependency of the generated crate that is fetched from crates.io.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RegistryDependency {
+  pub name: &'static str,
+  pub version: &'static str,
+}
+
+/// A file of the generated crate, relative to its folder.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlatformFile {
+  pub path: PathBuf,
+  pub content: String,
+}
+
+/// Generates the platform specific parts of a project: the crate type and the
+/// entry point that boots all repos and threads.
+pub trait PlatformGenerator {
+  /// Platform the backend generates for.
+  fn platform(&self) -> Platform;
+
+  /// Crate types of the `[lib]` section, a binary crate is generated if there are none.
+  fn crate_types(&self) -> Vec<&'static str> {
+    Vec::new()
+  }
+
+  /// Dependencies the entry point needs besides omnidux and the repos.
+  fn registry_dependencies(&self) -> Vec<RegistryDependency> {
+    Vec::new()
+  }
+
+  /// Renders the entry point and any other platform specific files.
+  fn render(&self, entry: &EntryData) -> Result<Vec<PlatformFile>, Box<dyn std::error::Error>>;
+}
+
+/// Renders an entry point template, which can include the shared `prelude` and `bootstrap`.
+/// Backends that can't spawn and block threads include `setup` instead of `bootstrap`,
+/// which only creates repos, threads and the context.
+fn render_entry(template: &str, entry: &EntryData) -> Result<String, Box<dyn std::error::Error>> {
+  render_template_with_partials(
+    template,
+    &[
+      ("prelude", ENTRY_PRELUDE_TEMPLATE),
+      ("setup", ENTRY_SETUP_TEMPLATE),
+      ("bootstrap", ENTRY_BOOTSTRAP_TEMPLATE),
+    ],
+    entry,
+  )
+}
+
+/// Renders the entry point of the debugger, which links every configured repo. It is
+/// the same for every platform as the debugger runs on the host.
+pub fn render_debug_entry(entry: &EntryData) -> Result<PlatformFile, Box<dyn std::error::Error>> {
+  Ok(PlatformFile {
+    path: PathBuf::from(DEBUG_ENTRY),
+    content: render_template(DEBUG_MAIN_TEMPLATE, entry)?,
+  })
+}
+
+/// Headless binary, used to run an application in tests and on CI.
+pub struct TestGenerator;
+
+impl PlatformGenerator for TestGenerator {
+  fn platform(&self) -> Platform {
+    Platform::Test
+  }
+
+  fn render(&self, entry: &EntryData) -> Result<Vec<PlatformFile>, Box<dyn std::error::Error>> {
+    Ok(vec![PlatformFile {
+      path: PathBuf::from("src/main.rs"),
+      content: render_entry(TEST_MAIN_TEMPLATE, entry)?,
+    }])
+  }
+}
+
+#[derive(Serialize)]
+struct HeaderData {
+  guard: String,
+}
+
+/// Static library linked into an Xcode project, booted through the C function
+/// `omnidux_main` which is declared in `include/<name>.h`.
+pub struct IOSGenerator;
+
+impl PlatformGenerator for IOSGenerator {
+  fn platform(&self) -> Platform {
+    Platform::IOS
+  }
+
+  fn crate_types(&self) -> Vec<&'static str> {
+    vec!["staticlib"]
+  }
+
+  fn render(&self, entry: &EntryData) -> Result<Vec<PlatformFile>, Box<dyn std::error::Error>> {
+    let guard = entry.name.chars()
+      .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' })
+      .collect::<String>() + "_H";
+
+    Ok(vec![
+      PlatformFile {
+        path: PathBuf::from("src/lib.rs"),
+        content: render_entry(IOS_LIB_TEMPLATE, entry)?,
+      },
+      PlatformFile {
+        path: PathBuf::from("include").join(format!("{}.h", entry.name)),
+        content: render_template(IOS_HEADER_TEMPLATE, &HeaderData { guard: guard })?,
+      },
+    ])
+  }
+}
+
+#[derive(Serialize)]
+struct WorkersData {
+  /// Name of the JavaScript bindings generated by wasm-bindgen.
+  bindings: String,
+}
+
+/// WebAssembly library, ready to be packaged by `wasm-pack`. The page boots it through
+/// `start` and drives the main thread through `poll`, other threads run in web workers
+/// started by `js/workers.js`.
+pub struct WebGenerator;
+
+impl PlatformGenerator for WebGenerator {
+  fn platform(&self) -> Platform {
+    Platform::Web
+  }
+
+  fn crate_types(&self) -> Vec<&'static str> {
+    vec!["cdylib", "rlib"]
+  }
+
+  fn registry_dependencies(&self) -> Vec<RegistryDependency> {
+    vec![RegistryDependency {
+      name: "wasm-bindgen",
+      version: "0.2",
+    }]
+  }
+
+  fn render(&self, entry: &EntryData) -> Result<Vec<PlatformFile>, Box<dyn std::error::Error>> {
+    let workers = WorkersData {
+      bindings: entry.name.replace('-', "_"),
+    };
+
+    Ok(vec![
+      PlatformFile {
+        path: PathBuf::from("src/lib.rs"),
+        content: render_entry(WEB_LIB_TEMPLATE, entry)?,
+      },
+      PlatformFile {
+        path: PathBuf::from("js/workers.js"),
+        content: render_template(WEB_WORKERS_TEMPLATE, &workers)?,
+      },
+    ])
+  }
+}
+
+/// Picks the backend for the target platform of a configuration.
+pub fn platform_generator(platform: &Platform) -> Box<dyn PlatformGenerator> {
+  match platform {
+    Platform::Test => Box::new(TestGenerator),
+    Platform::IOS => Box::new(IOSGenerator),
+    Platform::Web => Box::new(WebGenerator),
+  }
+}
@@ -1,13 +1,103 @@
-use std::io::prelude::*;
 use std::fs;
-use std::fs::File;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-use cargo_toml_builder::prelude::*;
+use serde::Serialize;
+
+use crate::config::{Configuration, Platform, RawConfiguration};
+use crate::config::manifest::{load_manifest, RepoManifest};
+use crate::config::validate::SYS_REPO_PREFIX;
+use crate::source::ResolvedRepo;
+
+use super::files::{plan_deletion, plan_file, FileChange};
+use super::platform::{platform_generator, render_debug_entry, EntryData, EntryRepo, PlatformFile, PlatformGenerator, RegistryDependency, TestGenerator};
+use super::template::{absolute_path, default_omnidux_path, display_path, relative_path, render_template};
+
+pub(crate) const CARGO_TEMPLATE: &str = include_str!("cargo_template.toml.hbs");
+const WORKSPACE_TEMPLATE: &str = include_str!("workspace_template.toml.hbs");
+
+/// Header of every generated file, files without it are never overwritten.
+const GENERATED_HEADER: &str = "# This file is generated by omnidux";
+/// Header of generated entry points and other source files.
+const GENERATED_SOURCE_HEADER: &str = "// This file is generated by omnidux";
+
+/// Name of the configuration snapshot inside of the generated crate, read by its main.rs.
+pub const CONFIG_FILE: &str = "omnidux.yaml";
 
 pub struct RustProjectBuilder {
   name: String,
   destination: String,
+  repos: Vec<ResolvedRepo>,
+  config: Option<Configuration>,
+  omnidux_path: PathBuf,
+  workspace: bool,
+  platform: Option<Box<dyn PlatformGenerator>>,
+}
+
+/// A path dependency of the generated crate.
+#[derive(Serialize)]
+pub(crate) struct Dependency {
+  pub name: String,
+  pub path: String,
+  /// Exact version the crate is pinned to.
+  pub version: Option<String>,
+  pub features: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub(crate) struct CargoData {
+  /// Whether the crate is owned by the generator, scaffolded crates are owned by the developer.
+  pub generated: bool,
+  pub name: String,
+  pub version: &'static str,
+  /// Crate types of the `[lib]` section, omitted if empty.
+  pub crate_types: Vec<&'static str>,
+  pub dependencies: Vec<Dependency>,
+  pub registry_dependencies: Vec<RegistryDependency>,
+}
+
+#[derive(Serialize)]
+struct WorkspaceData {
+  members: Vec<String>,
+}
+
+/// A crate the generated project depends on.
+struct Package {
+  name: String,
+  /// Folder of the crate.
+  path: PathBuf,
+  manifest: Option<RepoManifest>,
+}
+
+/// Reads a field of the `[package]` section from the Cargo.toml of a crate, if there is any.
+pub(crate) fn read_package_field(crate_path: &Path, field: &str) -> Option<String> {
+  let manifest = fs::read_to_string(crate_path.join("Cargo.toml")).ok()?;
+  let mut in_package = false;
+
+  for line in manifest.lines() {
+    let line = line.trim();
+    if line.starts_with('[') {
+      in_package = line == "[package]";
+    } else if in_package {
+      let mut parts = line.splitn(2, '=');
+      if parts.next().map(|key| key.trim()) == Some(field) {
+        let value = parts.next()?.trim();
+        return Some(value.trim_matches('"').to_string());
+      }
+    }
+  }
+
+  None
+}
+
+/// Refuses to plan over a file that was not generated by omnidux.
+fn check_generated(file: &Path) -> Result<(), Box<dyn std::error::Error>> {
+  if let Ok(existing) = fs::read_to_string(file) {
+    if !existing.starts_with(GENERATED_HEADER) {
+      return Err(format!("Refusing to overwrite {} which was not generated by omnidux", file.display()).into());
+    }
+  }
+
+  Ok(())
 }
 
 impl RustProjectBuilder {
@@ -16,7 +106,35 @@ impl RustProjectBuilder {
     Self {
       name: name,
       destination: "".to_string(),
+      repos: Vec::new(),
+      config: None,
+      omnidux_path: default_omnidux_path(),
+      workspace: false,
+      platform: None,
+    }
+  }
+
+  /// Sets the configuration the entry point is generated from. Unless a platform
+  /// generator was set, the one of the target platform is used.
+  pub fn set_config(mut self, config: Configuration) -> Self {
+    if self.platform.is_none() {
+      self.platform = Some(platform_generator(&config.target.platform));
     }
+    self.config = Some(config);
+    self
+  }
+
+  /// Sets the backend that generates the platform specific parts of the crate.
+  pub fn set_platform_generator(mut self, platform: Box<dyn PlatformGenerator>) -> Self {
+    self.platform = Some(platform);
+    self
+  }
+
+  /// Sets the repos the project depends on, paths are relative to the project folder
+  /// which is expected to be the parent of the destination.
+  pub fn set_repos(mut self, repos: Vec<ResolvedRepo>) -> Self {
+    self.repos = repos;
+    self
   }
 
   pub fn set_destination(mut self, destination: String) -> Self {
@@ -24,25 +142,225 @@ impl RustProjectBuilder {
     self
   }
 
-  #[allow(unused_must_use)]
-  pub fn build(self) -> Result<(), Box<dyn std::error::Error>> {
-    // Build toml configuration for cargo.
-    let toml = CargoToml::builder()
-      .name(&self.name)
-      .author("Autogenerated <>")
-      .version("1.0.0")
-      .build()?;
-    
-    // Prepare project folder 
-    let destination = format!("{d}/{n}-main-lib", d = self.destination, n = self.name);
-    let destination_path = Path::new(&destination);
-    fs::remove_dir_all(destination_path); 
-    fs::create_dir_all(destination_path)?;
-    fs::create_dir_all(format!("{d}/src", d = destination))?;
-
-    // Insert cargo
-    let mut file = File::create(format!("{d}/Cargo.toml", d = destination))?;
-    file.write_all(&toml.to_string().into_bytes())?;
-    Ok(())
+  /// Sets the folder containing `core` and the `sys` crates, defaults to the
+  /// checkout omnidux was built from.
+  pub fn set_omnidux_path(mut self, omnidux_path: PathBuf) -> Self {
+    self.omnidux_path = omnidux_path;
+    self
+  }
+
+  /// Additionally emits a workspace manifest into the project folder, covering the
+  /// generated crate and all local repos.
+  pub fn set_workspace(mut self, workspace: bool) -> Self {
+    self.workspace = workspace;
+    self
+  }
+
+  /// Backend of the project, a headless binary if there is neither a configuration nor a generator.
+  fn platform(&self) -> &dyn PlatformGenerator {
+    self.platform.as_ref().map(|platform| platform.as_ref()).unwrap_or(&TestGenerator)
+  }
+
+  /// Folder the repo sources are relative to, the parent of the destination.
+  fn project_path(&self) -> &Path {
+    Path::new(&self.destination).parent().unwrap_or_else(|| Path::new(""))
+  }
+
+  /// Folder of the generated crate.
+  fn crate_path(&self) -> PathBuf {
+    Path::new(&self.destination).join(format!("{n}-main-lib", n = self.name))
+  }
+
+  /// Locates the crate of a repo, preferring the name of its manifest over its Cargo.toml.
+  /// System repos are provided by the `omnidux_sys_*` crates.
+  fn package(&self, repo: &str) -> Package {
+    if repo.starts_with(SYS_REPO_PREFIX) {
+      let path = self.omnidux_path.join("sys").join(&repo[SYS_REPO_PREFIX.len()..]);
+      return Package {
+        name: format!("omnidux_sys_{}", repo[SYS_REPO_PREFIX.len()..].replace('-', "_")),
+        manifest: load_manifest(&path).ok().and_then(|manifest| manifest),
+        path: path,
+      };
+    }
+
+    let path = self.repos.iter()
+      .find(|resolved| resolved.name == repo)
+      .map(|resolved| self.project_path().join(&resolved.path))
+      .unwrap_or_else(|| self.project_path().join(repo));
+    let manifest = self.config.as_ref()
+      .and_then(|config| config.manifests.get(repo).cloned())
+      .or_else(|| load_manifest(&path).ok().and_then(|manifest| manifest));
+    let name = manifest.as_ref()
+      .map(|manifest| manifest.crate_name.clone())
+      .or_else(|| read_package_field(&path, "name"))
+      .unwrap_or_else(|| repo.to_string());
+
+    Package {
+      name: name,
+      path: path,
+      manifest: manifest,
+    }
+  }
+
+  /// Crates of the configured repos followed by the system repos used in the setup.
+  fn packages(&self) -> Vec<Package> {
+    let mut repos: Vec<String> = self.repos.iter().map(|repo| repo.name.clone()).collect();
+    if let Some(config) = &self.config {
+      for schedule in &config.setup {
+        if schedule.repo.starts_with(SYS_REPO_PREFIX) && !repos.contains(&schedule.repo) {
+          repos.push(schedule.repo.clone());
+        }
+      }
+    }
+
+    repos.iter().map(|repo| self.package(repo)).collect()
+  }
+
+  /// Data of the entry point which instantiates one repo per setup entry, spawns all
+  /// threads and blocks on the main thread.
+  pub fn entry_data(&self) -> Result<EntryData, Box<dyn std::error::Error>> {
+    let config = self.config.as_ref().ok_or("Missing configuration to generate the entry point from")?;
+
+    let mut crates = Vec::new();
+    let mut repos = Vec::new();
+    for (index, schedule) in config.setup.iter().enumerate() {
+      let crate_name = self.package(&schedule.repo).name.replace('-', "_");
+      if !crates.contains(&crate_name) {
+        crates.push(crate_name.clone());
+      }
+
+      repos.push(EntryRepo {
+        repo: schedule.repo.clone(),
+        crate_name: crate_name,
+        index: index,
+      });
+    }
+
+    Ok(EntryData {
+      name: self.name.clone(),
+      config_file: CONFIG_FILE,
+      crates: crates,
+      repos: repos,
+    })
+  }
+
+  /// Renders the entry point and other files of the platform backend followed by the
+  /// debugger entry point, relative to the folder of the generated crate.
+  pub fn render_entry(&self) -> Result<Vec<PlatformFile>, Box<dyn std::error::Error>> {
+    let entry = self.entry_data()?;
+    let mut files = self.platform().render(&entry)?;
+    files.push(render_debug_entry(&entry)?);
+    Ok(files)
+  }
+
+  /// Renders the Cargo.toml of the generated crate with a path dependency on
+  /// `omnidux_core` and every repo crate. Crates are pinned to the version found in
+  /// their Cargo.toml and get the features their manifest lists for the target platform.
+  pub fn render_cargo_toml(&self) -> Result<String, Box<dyn std::error::Error>> {
+    let crate_path = absolute_path(&self.crate_path());
+    let platform = self.config.as_ref().map(|_| self.platform().platform().name());
+
+    let core = Package {
+      name: "omnidux_core".to_string(),
+      path: self.omnidux_path.join("core"),
+      manifest: None,
+    };
+
+    let mut dependencies: Vec<Dependency> = Vec::new();
+    for package in Some(core).into_iter().chain(self.packages()) {
+      if dependencies.iter().any(|dependency| dependency.name == package.name) {
+        continue;
+      }
+
+      let features = match (&package.manifest, platform) {
+        (Some(manifest), Some(platform)) => manifest.features.get(platform).cloned().unwrap_or_default(),
+        _ => Vec::new(),
+      };
+
+      dependencies.push(Dependency {
+        path: display_path(&relative_path(&crate_path, &absolute_path(&package.path))),
+        version: read_package_field(&package.path, "version"),
+        name: package.name,
+        features: features,
+      });
+    }
+
+    let data = CargoData {
+      generated: true,
+      name: self.name.clone(),
+      version: "1.0.0",
+      crate_types: self.platform().crate_types(),
+      dependencies: dependencies,
+      registry_dependencies: self.platform().registry_dependencies(),
+    };
+
+    render_template(CARGO_TEMPLATE, &data)
+  }
+
+  /// Renders the workspace manifest of the project folder.
+  pub fn render_workspace(&self) -> Result<String, Box<dyn std::error::Error>> {
+    let project = absolute_path(self.project_path());
+
+    let mut members = vec![display_path(&relative_path(&project, &absolute_path(&self.crate_path())))];
+    for repo in &self.repos {
+      // Git checkouts are not part of the project sources.
+      if repo.commit.is_none() && self.project_path().join(&repo.path).join("Cargo.toml").exists() {
+        members.push(display_path(&relative_path(&project, &absolute_path(&self.project_path().join(&repo.path)))));
+      }
+    }
+
+    render_template(WORKSPACE_TEMPLATE, &WorkspaceData { members: members })
+  }
+
+  /// Compares the generated files with the ones on disk without writing anything.
+  /// User regions of existing files are carried over into the generated content.
+  pub fn plan(&self) -> Result<Vec<FileChange>, Box<dyn std::error::Error>> {
+    let destination = self.crate_path();
+    let mut changes = vec![plan_file(destination.join("Cargo.toml"), self.render_cargo_toml()?)?];
+
+    if self.workspace {
+      let workspace = self.project_path().join("Cargo.toml");
+      check_generated(&workspace)?;
+      changes.push(plan_file(workspace, self.render_workspace()?)?);
+    }
+
+    // Bootstrap the application from a snapshot of the resolved configuration.
+    if let Some(config) = &self.config {
+      let config_str = serde_yaml::to_string(&RawConfiguration::from(config.clone()))?;
+      changes.push(plan_file(destination.join(CONFIG_FILE), config_str)?);
+      let files = self.render_entry()?;
+
+      // Entry files of other backends are left behind when the platform changes,
+      // e.g. a src/main.rs next to the src/lib.rs of a library target.
+      let entry = self.entry_data()?;
+      let mut stale: Vec<PathBuf> = Vec::new();
+      for platform in Platform::ALL.iter() {
+        for file in platform_generator(platform).render(&entry)? {
+          if !files.iter().any(|current| current.path == file.path) && !stale.contains(&file.path) {
+            stale.push(file.path);
+          }
+        }
+      }
+
+      for file in files {
+        changes.push(plan_file(destination.join(file.path), file.content)?);
+      }
+      for path in stale {
+        changes.extend(plan_deletion(destination.join(path), GENERATED_SOURCE_HEADER)?);
+      }
+    }
+
+    Ok(changes)
+  }
+
+  /// Writes all generated files whose content changed, other files inside of the
+  /// destination are left untouched.
+  pub fn build(self) -> Result<Vec<FileChange>, Box<dyn std::error::Error>> {
+    let changes = self.plan()?;
+    for change in &changes {
+      change.apply()?;
+    }
+
+    Ok(changes)
   }
 }
@@ -0,0 +1,264 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::config::{build_config_from_file, build_config_from_str, Configuration, Platform, ThreadType, CURRENT_VERSION};
+use crate::config::manifest::MANIFEST_FILE;
+
+use super::files::{plan_file, FileChange};
+use super::rust::{CargoData, Dependency, CARGO_TEMPLATE, read_package_field};
+use super::template::{absolute_path, display_path, relative_path, render_template};
+
+const PROJECT_CONFIG_TEMPLATE: &str = include_str!("project_config.yaml.hbs");
+const REPO_MANIFEST_TEMPLATE: &str = include_str!("repo_manifest.yaml.hbs");
+const REPO_LIB_TEMPLATE: &str = include_str!("repo_lib.rs.hbs");
+
+/// Folder new repos are created in, relative to the project folder.
+pub const REPOS_FOLDER: &str = "repos";
+
+/// Name of the repo every new project starts with.
+pub const INITIAL_REPO: &str = "core";
+
+#[derive(Serialize)]
+struct ProjectData<'a> {
+  name: &'a str,
+  platform: &'static str,
+  engine: &'static str,
+  /// Type and driver of the worker thread.
+  worker_type: &'static str,
+  worker_driver: &'static str,
+}
+
+#[derive(Serialize)]
+struct RepoData<'a> {
+  repo: &'a str,
+  crate_name: String,
+  task: String,
+  capsule: String,
+}
+
+/// Converts a name like `kanban-board` into `KanbanBoard`.
+fn pascal_case(name: &str) -> String {
+  name.split(|c: char| !c.is_alphanumeric())
+    .filter(|part| !part.is_empty())
+    .map(|part| {
+      let mut chars = part.chars();
+      chars.next().map(|first| first.to_uppercase().chain(chars).collect::<String>()).unwrap_or_default()
+    })
+    .collect()
+}
+
+/// Converts a name like `kanban-board` into `kanban_board`.
+fn snake_case(name: &str) -> String {
+  name.chars()
+    .map(|c| if c.is_alphanumeric() { c.to_ascii_lowercase() } else { '_' })
+    .collect()
+}
+
+/// Plans a file that must not exist yet.
+fn plan_new_file(path: PathBuf, content: String) -> Result<FileChange, Box<dyn std::error::Error>> {
+  if path.exists() {
+    return Err(format!("{} already exists", path.display()).into());
+  }
+
+  Ok(plan_file(path, content)?)
+}
+
+/// Plans the skeleton of a new project inside of `<parent>/<name>`, including its initial repo.
+pub fn new_project(parent: &Path, name: &str, platform: &Platform, omnidux_path: &Path)
+  -> Result<Vec<FileChange>, Box<dyn std::error::Error>> {
+  // Web applications run in the browser, their workers are web workers.
+  let (engine, worker_type, worker_driver) = match platform {
+    Platform::Web => ("wasm", "webworker", "worker-direct"),
+    Platform::IOS | Platform::Test => ("native", "thread", "mpsc-fifo"),
+  };
+  let data = ProjectData {
+    name: name,
+    platform: platform.name(),
+    engine: engine,
+    worker_type: worker_type,
+    worker_driver: worker_driver,
+  };
+
+  let config_file = parent.join(name).join(format!("omnidux.{}.yaml", platform.name()));
+  if config_file.exists() {
+    return Err(format!("{} already exists", config_file.display()).into());
+  }
+
+  let content = render_template(PROJECT_CONFIG_TEMPLATE, &data)?;
+  let config = build_config_from_str(&content)?;
+  plan_repo(&config_file, &content, &config, INITIAL_REPO, omnidux_path)
+}
+
+fn indentation(line: &str) -> usize {
+  line.len() - line.trim_start().len()
+}
+
+/// Whether the line holds yaml content, blank and comment lines don't.
+fn is_content(line: &str) -> bool {
+  let trimmed = line.trim();
+  !trimmed.is_empty() && !trimmed.starts_with('#')
+}
+
+/// End of the block that belongs to the key at the given line, behind its last content line.
+fn block_end(lines: &[String], key: usize) -> usize {
+  let indent = indentation(&lines[key]);
+  let mut end = key + 1;
+  for (index, line) in lines.iter().enumerate().skip(key + 1) {
+    if is_content(line) {
+      if indentation(line) <= indent && !line.trim_start().starts_with("- ") {
+        break;
+      }
+      if indentation(line) < indent {
+        break;
+      }
+      end = index + 1;
+    }
+  }
+  end
+}
+
+/// Finds the line of a mapping key by its path, only considering keys of the given block.
+fn find_key(lines: &[String], path: &[&str]) -> Option<usize> {
+  let (mut start, mut end, mut indent) = (0, lines.len(), 0);
+  let mut found = None;
+
+  for (depth, key) in path.iter().enumerate() {
+    if depth > 0 {
+      indent = lines[start..end].iter().find(|line| is_content(line)).map(|line| indentation(line))?;
+    }
+
+    let index = (start..end).find(|&index| {
+      let line = &lines[index];
+      is_content(line) && indentation(line) == indent && line.trim_start().starts_with(&format!("{}:", key))
+    })?;
+
+    found = Some(index);
+    start = index + 1;
+    end = block_end(lines, index);
+  }
+
+  found
+}
+
+/// Appends an item to a yaml list in place, keeping comments and formatting of the
+/// rest of the document. `item` holds the lines of the item without the leading `- `.
+fn append_list_item(content: &str, path: &[&str], item: &[String]) -> Result<String, String> {
+  let mut lines: Vec<String> = content.lines().map(|line| line.to_string()).collect();
+  let describe = path.join(".");
+
+  // Lists that are only defined by an extended configuration are added to this one.
+  let key = match find_key(&lines, path) {
+    Some(key) => key,
+    None if path.len() == 1 => {
+      if lines.last().map(|line| !line.trim().is_empty()).unwrap_or(false) {
+        lines.push(String::new());
+      }
+      lines.push(format!("{}:", path[0]));
+      lines.len() - 1
+    },
+    None => return Err(format!("`{}` is missing", describe)),
+  };
+  let key_indent = indentation(&lines[key]);
+
+  // An inline empty list is turned into a block list.
+  let value = lines[key].splitn(2, ':').nth(1).unwrap_or("").split(" #").next().unwrap_or("").trim().to_string();
+  match value.as_str() {
+    "" => {},
+    "[]" => lines[key] = format!("{}{}:", " ".repeat(key_indent), path[path.len() - 1]),
+    _ => return Err(format!("`{}` is not a block list, add the entry manually", describe)),
+  }
+
+  let end = block_end(&lines, key);
+  let item_indent = lines[key + 1..end].iter()
+    .find(|line| is_content(line) && line.trim_start().starts_with("- "))
+    .map(|line| indentation(line))
+    .unwrap_or(key_indent + 2);
+
+  let mut inserted = Vec::new();
+  for (i, line) in item.iter().enumerate() {
+    let prefix = if i == 0 { "- " } else { "  " };
+    inserted.push(format!("{}{}{}", " ".repeat(item_indent), prefix, line));
+  }
+
+  lines.splice(end..end, inserted);
+  let mut updated = lines.join("\n");
+  if content.ends_with('\n') {
+    updated.push('\n');
+  }
+  Ok(updated)
+}
+
+/// Plans a new repo inside of the project folder of the given configuration file and
+/// registers it in the `repos`, `setup` and `scopes` of the configuration.
+pub fn new_repo(config_file: &Path, name: &str, omnidux_path: &Path) -> Result<Vec<FileChange>, Box<dyn std::error::Error>> {
+  if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+    return Err(format!("Invalid repo name `{}`, use letters, digits, `-` and `_`", name).into());
+  }
+
+  let content = fs::read_to_string(config_file)?;
+  let config = build_config_from_file(config_file.to_string_lossy().into_owned())?;
+  plan_repo(config_file, &content, &config, name, omnidux_path)
+}
+
+/// Plans the files of a new repo and the configuration file with the repo registered.
+fn plan_repo(config_file: &Path, content: &str, config: &Configuration, name: &str, omnidux_path: &Path)
+  -> Result<Vec<FileChange>, Box<dyn std::error::Error>> {
+  let project = config_file.parent().unwrap_or_else(|| Path::new(""));
+  if config.has_repo(name) {
+    return Err(format!("Repo `{}` is already registered in {}", name, config_file.display()).into());
+  }
+
+  let folder = project.join(REPOS_FOLDER).join(name);
+  let data = RepoData {
+    repo: name,
+    crate_name: snake_case(&format!("{}_{}", config.name, name)),
+    task: format!("{}Task", pascal_case(name)),
+    capsule: format!("{}Capsule", pascal_case(name)),
+  };
+
+  // The repo depends on the same omnidux_core as the generated project.
+  let core = omnidux_path.join("core");
+  let cargo = CargoData {
+    generated: false,
+    name: data.crate_name.clone(),
+    version: "0.1.0",
+    crate_types: Vec::new(),
+    dependencies: vec![Dependency {
+      name: "omnidux_core".to_string(),
+      path: display_path(&relative_path(&absolute_path(&folder), &absolute_path(&core))),
+      version: read_package_field(&core, "version"),
+      features: Vec::new(),
+    }],
+    registry_dependencies: Vec::new(),
+  };
+
+  // Tasks of the repo run on the first worker, or on the main thread if there is none.
+  let setup_path: &[&str] = match config.version {
+    CURRENT_VERSION => &["application", "repos"],
+    _ => &["setup"],
+  };
+  let target = config.threads.iter()
+    .find(|thread| thread.thread_type == ThreadType::Thread || thread.thread_type == ThreadType::WebWorker)
+    .or_else(|| config.threads.first())
+    .map(|thread| thread.name.clone())
+    .unwrap_or_else(|| "\"*\"".to_string());
+
+  let mut updated = content.to_string();
+  for (path, item) in vec![
+    (&["repos"][..], vec!["type: local".to_string(), format!("source: ./{}/{}", REPOS_FOLDER, name)]),
+    (setup_path, vec![format!("repo: {}", name), format!("target: {}", target)]),
+    (&["scopes"][..], vec![format!("repo: {}", name)]),
+  ] {
+    updated = append_list_item(&updated, path, &item)
+      .map_err(|error| format!("Failed to register repo in {}: {}", config_file.display(), error))?;
+  }
+
+  Ok(vec![
+    plan_new_file(folder.join("Cargo.toml"), render_template(CARGO_TEMPLATE, &cargo)?)?,
+    plan_new_file(folder.join(MANIFEST_FILE), render_template(REPO_MANIFEST_TEMPLATE, &data)?)?,
+    plan_new_file(folder.join("src/lib.rs"), render_template(REPO_LIB_TEMPLATE, &data)?)?,
+    plan_file(config_file.to_path_buf(), updated)?,
+  ])
+}
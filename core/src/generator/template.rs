@@ -0,0 +1,86 @@
+use std::env;
+use std::path::{Component, Path, PathBuf};
+
+use handlebars::{Handlebars, no_escape};
+use serde::Serialize;
+
+/// Environment variable that points to the folder containing `core` and the `sys` crates.
+pub const OMNIDUX_PATH_VAR: &str = "OMNIDUX_PATH";
+
+/// Folder containing `core` and the `sys` crates, resolved when omnidux runs: `OMNIDUX_PATH`
+/// if it is set, otherwise the closest folder around the running executable that contains
+/// `core/Cargo.toml`, e.g. the checkout omnidux was built in. Falls back to the working directory.
+pub fn default_omnidux_path() -> PathBuf {
+  if let Some(path) = env::var_os(OMNIDUX_PATH_VAR) {
+    return PathBuf::from(path);
+  }
+
+  env::current_exe().ok()
+    .and_then(|exe| {
+      exe.ancestors()
+        .find(|folder| folder.join("core").join("Cargo.toml").exists())
+        .map(Path::to_path_buf)
+    })
+    .unwrap_or_else(|| PathBuf::from("."))
+}
+
+/// Renders one of the generator templates, the output is not escaped.
+pub fn render_template<T: Serialize>(template: &str, data: &T) -> Result<String, Box<dyn std::error::Error>> {
+  render_template_with_partials(template, &[], data)
+}
+
+/// Renders a template that includes the given `(name, template)` partials by `{{> name}}`.
+pub fn render_template_with_partials<T: Serialize>(
+  template: &str,
+  partials: &[(&str, &str)],
+  data: &T,
+) -> Result<String, Box<dyn std::error::Error>> {
+  let mut handlebars = Handlebars::new();
+  handlebars.register_escape_fn(no_escape);
+  for (name, partial) in partials {
+    handlebars.register_partial(name, partial)?;
+  }
+  handlebars.register_template_string("template", template)?;
+  Ok(handlebars.render("template", data)?)
+}
+
+/// Makes a path absolute, resolving the part of it that already exists on disk.
+pub fn absolute_path(path: &Path) -> PathBuf {
+  let mut existing = path;
+  let mut missing = Vec::new();
+
+  loop {
+    if let Ok(canonical) = existing.canonicalize() {
+      return missing.iter().rev().fold(canonical, |path, component| path.join(component));
+    }
+
+    match (existing.parent(), existing.file_name()) {
+      (Some(parent), Some(name)) => {
+        missing.push(name.to_os_string());
+        existing = if parent.as_os_str().is_empty() { Path::new(".") } else { parent };
+      },
+      _ => return path.to_path_buf(),
+    }
+  }
+}
+
+/// Path of `to` relative to the folder `from`, both need to be absolute.
+pub fn relative_path(from: &Path, to: &Path) -> PathBuf {
+  let from: Vec<Component> = from.components().collect();
+  let to: Vec<Component> = to.components().collect();
+  let common = from.iter().zip(to.iter()).take_while(|(a, b)| a == b).count();
+
+  let mut relative = PathBuf::new();
+  for _ in common..from.len() {
+    relative.push("..");
+  }
+  for component in &to[common..] {
+    relative.push(component.as_os_str());
+  }
+  relative
+}
+
+/// Formats a path the same way on every platform.
+pub fn display_path(path: &Path) -> String {
+  path.to_string_lossy().replace('\\', "/")
+}
@@ -0,0 +1,232 @@
+use std::fs;
+use std::path::Path;
+
+use crate::config::{Configuration, Scope};
+
+/// A capsule of `owner` that is read by `reader`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CapsuleRead {
+  pub reader: String,
+  pub owner: String,
+  pub capsule: String,
+}
+
+/// Output format of the graph.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GraphFormat {
+  Dot,
+  Mermaid,
+}
+
+/// Identifier of a node, names like `sys/shadow-renderer` are not valid identifiers.
+fn node_id(prefix: &str, name: &str) -> String {
+  let name: String = name.chars().map(|c| if c.is_ascii_alphanumeric() { c } else { '_' }).collect();
+  format!("{}_{}", prefix, name)
+}
+
+/// Escapes a label for a quoted DOT string.
+fn escape_dot(label: &str) -> String {
+  label.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+/// Escapes a label for a quoted Mermaid string, which only understands entity codes.
+fn escape_mermaid(label: &str) -> String {
+  label.replace('#', "#35;").replace('"', "#quot;").replace('<', "#lt;").replace('>', "#gt;")
+}
+
+/// Repos of the configuration, followed by the system repos that are only part of the setup.
+fn repo_names(config: &Configuration) -> Vec<String> {
+  let mut names: Vec<String> = config.repos.iter().map(|repo| repo.name()).collect();
+  for schedule in &config.setup {
+    if !names.contains(&schedule.repo) {
+      names.push(schedule.repo.clone());
+    }
+  }
+  names
+}
+
+/// Finds the repo whose crate is referenced by a `capsule_get!` call.
+fn repo_of_crate(config: &Configuration, crate_name: &str) -> Option<String> {
+  repo_names(config).into_iter().find(|repo| {
+    let manifest_crate = config.manifests.get(repo).map(|manifest| manifest.crate_name.replace('-', "_"));
+    manifest_crate.as_ref().map(|name| name.as_str()) == Some(crate_name)
+      || repo.replace(|c: char| !c.is_ascii_alphanumeric(), "_") == crate_name
+      || format!("omnidux_{}", repo.replace(|c: char| !c.is_ascii_alphanumeric(), "_")) == crate_name
+  })
+}
+
+/// Collects the `capsule_get!` calls of all rust files inside of a folder.
+fn scan_capsule_gets(folder: &Path, calls: &mut Vec<(String, String)>) {
+  let entries = match fs::read_dir(folder) {
+    Ok(entries) => entries,
+    Err(_) => return,
+  };
+
+  for entry in entries.filter_map(|entry| entry.ok()) {
+    let path = entry.path();
+    if path.is_dir() {
+      if path.file_name().map(|name| name != "target").unwrap_or(false) {
+        scan_capsule_gets(&path, calls);
+      }
+      continue;
+    }
+
+    if path.extension().map(|extension| extension != "rs").unwrap_or(true) {
+      continue;
+    }
+
+    let content = fs::read_to_string(&path).unwrap_or_default();
+    for call in content.split("capsule_get!").skip(1) {
+      let args: Vec<&str> = call.trim_start()
+        .trim_start_matches(|c| c == '(' || c == '[' || c == '{')
+        .splitn(4, ',')
+        .map(|arg| arg.trim())
+        .collect();
+      if args.len() >= 3 {
+        calls.push((args[1].to_string(), args[2].to_string()));
+      }
+    }
+  }
+}
+
+/// Detects capsule reads across repos, declared by the `requires` of their manifests
+/// or found as `capsule_get!` calls inside of their sources.
+pub fn detect_capsule_reads(config: &Configuration, project: &Path) -> Vec<CapsuleRead> {
+  let mut reads = Vec::new();
+  let mut push = |read: CapsuleRead| {
+    if read.reader != read.owner && !reads.contains(&read) {
+      reads.push(read);
+    }
+  };
+
+  let mut names: Vec<&String> = config.manifests.keys().collect();
+  names.sort();
+  for name in names {
+    for requirement in &config.manifests[name].requires {
+      push(CapsuleRead {
+        reader: name.clone(),
+        owner: requirement.repo.clone(),
+        capsule: requirement.capsule.clone(),
+      });
+    }
+  }
+
+  for repo in &config.repos {
+    let mut calls = Vec::new();
+    scan_capsule_gets(&project.join(repo.path()), &mut calls);
+
+    for (crate_name, capsule) in calls {
+      if let Some(owner) = repo_of_crate(config, &crate_name) {
+        push(CapsuleRead {
+          reader: repo.name(),
+          owner: owner,
+          capsule: capsule,
+        });
+      }
+    }
+  }
+
+  reads
+}
+
+fn write_dot_scopes(scopes: &[Scope], depth: usize, output: &mut Vec<String>) {
+  let indent = "  ".repeat(depth);
+  for scope in scopes {
+    output.push(format!("{}subgraph {} {{", indent, node_id("cluster_scope", &scope.repo)));
+    output.push(format!("{}  label=\"{}\";", indent, escape_dot(&scope.repo)));
+    output.push(format!("{}  {};", indent, node_id("repo", &scope.repo)));
+    if let Some(children) = &scope.scopes {
+      write_dot_scopes(children, depth + 1, output);
+    }
+    output.push(format!("{}}}", indent));
+  }
+}
+
+/// Renders the configuration as Graphviz DOT.
+pub fn to_dot(config: &Configuration, capsule_reads: &[CapsuleRead]) -> String {
+  let mut output = vec![format!("digraph \"{}\" {{", escape_dot(&config.name)), "  rankdir=LR;".to_string()];
+
+  for repo in repo_names(config) {
+    output.push(format!("  {} [label=\"{}\", shape=component];", node_id("repo", &repo), escape_dot(&repo)));
+  }
+  for thread in &config.threads {
+    output.push(format!(
+      "  {} [label=\"{}\\n{}\", shape=box];",
+      node_id("thread", &thread.name), escape_dot(&thread.name), thread.driver.name(),
+    ));
+  }
+
+  write_dot_scopes(&config.scopes, 1, &mut output);
+
+  for schedule in &config.setup {
+    for &thread in &schedule.target_threads {
+      output.push(format!(
+        "  {} -> {};",
+        node_id("repo", &schedule.repo), node_id("thread", &config.threads[thread].name),
+      ));
+    }
+  }
+  for read in capsule_reads {
+    output.push(format!(
+      "  {} -> {} [label=\"{}\", style=dashed];",
+      node_id("repo", &read.reader), node_id("repo", &read.owner), escape_dot(&read.capsule),
+    ));
+  }
+
+  output.push("}".to_string());
+  output.join("\n") + "\n"
+}
+
+fn write_mermaid_scopes(scopes: &[Scope], depth: usize, output: &mut Vec<String>) {
+  let indent = "  ".repeat(depth);
+  for scope in scopes {
+    output.push(format!("{}subgraph {} [\"{}\"]", indent, node_id("scope", &scope.repo), escape_mermaid(&scope.repo)));
+    output.push(format!("{}  {}", indent, node_id("repo", &scope.repo)));
+    if let Some(children) = &scope.scopes {
+      write_mermaid_scopes(children, depth + 1, output);
+    }
+    output.push(format!("{}end", indent));
+  }
+}
+
+/// Renders the configuration as a Mermaid flowchart.
+pub fn to_mermaid(config: &Configuration, capsule_reads: &[CapsuleRead]) -> String {
+  let mut output = vec!["graph LR".to_string()];
+
+  for repo in repo_names(config) {
+    output.push(format!("  {}[\"{}\"]", node_id("repo", &repo), escape_mermaid(&repo)));
+  }
+  for thread in &config.threads {
+    output.push(format!(
+      "  {}[[\"{} ({})\"]]",
+      node_id("thread", &thread.name), escape_mermaid(&thread.name), thread.driver.name(),
+    ));
+  }
+
+  write_mermaid_scopes(&config.scopes, 1, &mut output);
+
+  for schedule in &config.setup {
+    for &thread in &schedule.target_threads {
+      output.push(format!(
+        "  {} --> {}",
+        node_id("repo", &schedule.repo), node_id("thread", &config.threads[thread].name),
+      ));
+    }
+  }
+  for read in capsule_reads {
+    output.push(format!(
+      "  {} -.->|\"{}\"| {}",
+      node_id("repo", &read.reader), escape_mermaid(&read.capsule), node_id("repo", &read.owner),
+    ));
+  }
+
+  output.join("\n") + "\n"
+}
+
+/// Renders the configuration in the given format.
+pub fn render(config: &Configuration, format: GraphFormat, capsule_reads: &[CapsuleRead]) -> String {
+  match format {
+    GraphFormat::Dot => to_dot(config, capsule_reads),
+    GraphFormat::Mermaid => to_mermaid(config, capsule_reads),
+  }
+}
@@ -1,23 +1,31 @@
 #[macro_use()]
 extern crate serde;
 extern crate serde_yaml;
-extern crate cargo_toml_builder;
+extern crate serde_json;
+extern crate schemars;
+extern crate yaml_rust;
+extern crate handlebars;
 extern crate static_assertions;
 extern crate futures;
 extern crate evmap;
+extern crate num_cpus;
 extern crate proc_macro_hack;
 extern crate omnidux_macros;
 
 pub mod config;
 pub mod generator;
+pub mod graph;
+pub mod source;
 pub mod task;
 pub mod threads;
 pub mod uuid;
+pub mod watch;
 #[macro_use]
 pub mod repo;
 #[macro_use]
 pub mod scheduler;
 pub mod capsule;
+pub mod debug;
 
 // Reexporting macros.
 use proc_macro_hack::proc_macro_hack;
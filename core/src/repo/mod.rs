@@ -1,19 +1,28 @@
 use std::any::Any;
 
-use crate::task::Task;
+use crate::task::{Task, TaskError, TaskOutcome};
 use crate::config::Schedule;
+use crate::capsule::DebugCapsule;
 
 pub trait Repository {
   fn as_any(&self) -> &dyn Any;
   fn get_schedule_config(&self) -> Schedule;
-  fn handle_schedule(&self, task: &Task);
+  /// Hands the task to its handler and returns its output, or the future of an async
+  /// handler. Fails if the payload doesn't match its input type or the handler panicked.
+  fn handle_schedule(&self, task: &Task) -> Result<TaskOutcome, TaskError>;
   fn has_ownership(&self, uuid: usize) -> bool;
+  /// Lists the uuid and name of every task handler of the repo.
+  fn get_tasks(&self) -> Vec<(usize, &'static str)>;
+  /// Lists the names of all capsules of the repo.
+  fn get_capsule_names(&self) -> Vec<&'static str>;
+  /// Gives untyped access to a capsule by its name.
+  fn get_capsule(&self, name: &str) -> Option<&dyn DebugCapsule>;
 }
 
 #[macro_export]
 macro_rules! create_task_handler {
   ($($name:ident),* ,) => {
-      |handlers: &mut Vec<Box<dyn TaskHandler + Sync + Send>>, counter: &mut usize| {
+      |handlers: &mut Vec<Box<dyn omnidux_core::task::AnyTaskHandler + Sync + Send>>, counter: &mut usize| {
         $(
           println!("Registered task handler for uuid {uuid}", uuid = *counter);
           handlers.push(
@@ -25,6 +34,13 @@ macro_rules! create_task_handler {
   }
 }
 
+#[macro_export]
+macro_rules! create_task_names {
+  ($($name:ident),* ,) => {
+    vec![$(stringify!($name),)*]
+  }
+}
+
 #[macro_export]
 macro_rules! create_task_traits {
   // Tail
@@ -55,6 +71,26 @@ macro_rules! create_capsule_def {
   };
 }
 
+#[macro_export]
+macro_rules! create_capsule_lookup {
+  ($($name:ident),* ,) => {
+    impl Capsules {
+      pub fn names(&self) -> Vec<&'static str> {
+        vec![$(stringify!($name),)*]
+      }
+
+      pub fn get(&self, name: &str) -> Option<&dyn omnidux_core::capsule::DebugCapsule> {
+        match name {
+          $(
+            stringify!($name) => Some(&*self.$name),
+          )*
+          _ => None,
+        }
+      }
+    }
+  };
+}
+
 #[macro_export]
 macro_rules! create_capsule_holder {
   ($($name:ident),* ,) => {
@@ -77,11 +113,12 @@ macro_rules! create_repo {
 
     // Struct that hols all capsules.
     create_capsule_def! $capsules;
+    create_capsule_lookup! $capsules;
 
     pub struct Repository {
       pub schedule_config: Schedule,
       pub start_index: usize,
-      pub handlers: Vec<Box<dyn TaskHandler + Sync + Send>>,
+      pub handlers: Vec<Box<dyn omnidux_core::task::AnyTaskHandler + Sync + Send>>,
       pub capsules: Capsules,
     }
 
@@ -111,18 +148,36 @@ macro_rules! create_repo {
         self
       }
 
-      fn handle_schedule(&self, task: &Task) {
-        let local_uuid = self.start_index - task.uuid;
-        self.handlers.get(local_uuid).unwrap().handle(task);
+      fn handle_schedule(&self, task: &Task) -> Result<omnidux_core::task::TaskOutcome, omnidux_core::task::TaskError> {
+        let local_uuid = task.uuid - self.start_index;
+        self.handlers.get(local_uuid)
+          .ok_or(omnidux_core::task::TaskError::UnknownTask(task.uuid))?
+          .handle_any(task)
       }
 
       fn has_ownership(&self, inner_uuid: usize) -> bool {
-        self.start_index <= inner_uuid && inner_uuid <= (self.start_index + self.handlers.len())
+        self.start_index <= inner_uuid && inner_uuid < (self.start_index + self.handlers.len())
       }
       
       fn get_schedule_config(&self) -> Schedule {
         self.schedule_config.clone()
       }
+
+      fn get_tasks(&self) -> Vec<(usize, &'static str)> {
+        let names: Vec<&'static str> = create_task_names! $tasks;
+        names.into_iter()
+          .enumerate()
+          .map(|(i, name)| (self.start_index + i, name))
+          .collect()
+      }
+
+      fn get_capsule_names(&self) -> Vec<&'static str> {
+        self.capsules.names()
+      }
+
+      fn get_capsule(&self, name: &str) -> Option<&dyn omnidux_core::capsule::DebugCapsule> {
+        self.capsules.get(name)
+      }
     }
 
     // Traits for tasks
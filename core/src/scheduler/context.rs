@@ -1,55 +1,283 @@
 use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
 
+use crate::config::ResolvedScale;
 use crate::repo::Repository;
 use crate::threads::ThreadSender;
-use crate::task::Task;
-use crate::scheduler::strategy::ScheduleStrategy;
+use futures::future::FutureExt;
+
+use crate::task::{HandlerFuture, Task, TaskError, TaskOutcome, TaskSignature};
+use crate::task::completion::{Completion, TaskFuture, TaskOutput};
+use crate::scheduler::strategy::{EligibleThread, EligibleThreads, ScheduleStrategy};
+use crate::scheduler::timer::{Clock, PickTargets, SystemClock, TimerHandle, TimerService};
+
+/// Gets notified with the thread uuid and the task after a task was handled.
+pub type ExecutionListener = Arc<dyn Fn(usize, &Task) + Send + Sync>;
+
+/// A task whose async handler has not finished yet, polled by the thread it was routed to.
+pub type PendingTask = HandlerFuture<Result<(), TaskError>>;
+
+/// Notifies the listener and the future of a handled task.
+fn finish(
+  listener: &Option<ExecutionListener>,
+  thread_uuid: usize,
+  task: &Task,
+  result: Result<TaskOutput, TaskError>,
+) -> Result<(), TaskError> {
+  let status = match &result {
+    Ok(_) => Ok(()),
+    Err(error) => Err(error.clone()),
+  };
+  if let (Ok(_), Some(listener)) = (&status, listener) {
+    listener(thread_uuid, task);
+  }
+  if let Some(completion) = &task.completion {
+    completion.complete(result);
+  }
+  status
+}
 
 #[derive(Clone)]
 pub struct Context {
   repos: Vec<Arc<dyn Repository + Send + Sync>>,
-  senders: Vec<ThreadSender>,
+  /// Sender of every thread, indexed by the thread uuid.
+  routes: Vec<Option<ThreadSender>>,
+  /// Threads every repo may be scheduled on, in the order of the repos.
+  eligible: Vec<EligibleThreads>,
+  /// Scale of every repo resolved for this machine, in the order of the repos.
+  scales: Vec<ResolvedScale>,
+  listener: Option<ExecutionListener>,
+  /// Delayed and periodic tasks.
+  timers: Arc<TimerService>,
 }
 
 impl Context {
   /// Creates a new context by list of repositories and senders.
   pub fn new(repos: Vec<Arc<dyn Repository + Send + Sync>>, senders: Vec<ThreadSender>) -> Context {
+    let mut routes = Vec::new();
+    for sender in senders {
+      let uuid = sender.uuid;
+      if routes.len() <= uuid {
+        routes.resize(uuid + 1, None);
+      }
+      routes[uuid] = Some(sender);
+    }
+
+    // Scales depend on the machine, so they are resolved now that the application boots.
+    let available_parallelism = num_cpus::get();
+    let scales: Vec<ResolvedScale> = repos.iter()
+      .map(|repo| repo.get_schedule_config().resolve_scale(available_parallelism))
+      .collect();
+
+    // Map the resolved targets of every repo onto the threads that can be routed to,
+    // a repo runs on no more threads than its `max` allows.
+    let eligible = repos.iter()
+      .zip(&scales)
+      .map(|(repo, scale)| EligibleThreads::new(repo.get_schedule_config().target_threads.iter()
+        .filter_map(|&uuid| routes.get(uuid).and_then(|route| route.as_ref()))
+        .take(scale.max)
+        .map(|sender| EligibleThread {
+          uuid: sender.uuid,
+          name: sender.name.clone(),
+          pending: sender.pending.clone(),
+        })
+        .collect()))
+      .collect();
+
     Context {
       repos: repos,
-      senders: senders,
+      routes: routes,
+      eligible: eligible,
+      scales: scales,
+      listener: None,
+      timers: Arc::new(TimerService::new(Arc::new(SystemClock))),
     }
   }
 
-  // Global schedule handle of a task.
-  pub fn handle_schedule(&self, thread_uuid: usize, task: &Task) {
-    // Skipping execution as it was scheduled for a different thread.
-    if !task.execution_targets.as_ref().unwrap().contains(&thread_uuid) {
-      return;
-    }
+  /// Sets a listener that is called after every handled task, must be set before threads are spawned.
+  pub fn set_execution_listener(&mut self, listener: ExecutionListener) {
+    self.listener = Some(listener);
+  }
 
-    for repo in self.repos.iter() {
-      if !repo.has_ownership(task.uuid) {
-        continue;
-      }
+  /// Replaces the clock of the timer service, must be set before timers are added.
+  pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+    self.timers = Arc::new(TimerService::new(clock));
+  }
+
+  /// Lists all repositories of the context.
+  pub fn get_repos(&self) -> &[Arc<dyn Repository + Send + Sync>] {
+    &self.repos
+  }
 
-      repo.handle_schedule(task);
-      break;
+  // Global schedule handle of a task, only called by the threads the task was routed to.
+  // Completes the future of the task with the output of its handler or the error.
+  // Async handlers return the pending task, which finishes the task once it resolves.
+  pub fn handle_schedule(&self, thread_uuid: usize, task: &Task) -> Result<Option<PendingTask>, TaskError> {
+    debug_assert!(task.execution_targets.as_ref().map(|targets| targets.contains(&thread_uuid)).unwrap_or(false));
+
+    let outcome = self.repos.iter()
+      .find(|repo| repo.has_ownership(task.uuid))
+      .ok_or(TaskError::UnknownTask(task.uuid))
+      .and_then(|repo| repo.handle_schedule(task));
+
+    match outcome {
+      Ok(TaskOutcome::Ready(output)) => finish(&self.listener, thread_uuid, task, Ok(output)).map(|_| None),
+      Ok(TaskOutcome::Pending(future)) => {
+        let listener = self.listener.clone();
+        let task = task.clone();
+        Ok(Some(future.map(move |result| finish(&listener, thread_uuid, &task, result)).boxed()))
+      },
+      Err(error) => finish(&self.listener, thread_uuid, task, Err(error)).map(|_| None),
     }
   }
 
-  pub fn schedule<T: ScheduleStrategy>(&self, mut task: Task) {
+  /// Schedules the task on the threads picked by the strategy of `T`, the returned
+  /// future resolves with the output of the handler. Dropping it doesn't cancel the task.
+  pub fn schedule<T: ScheduleStrategy + TaskSignature>(&self, task: Task) -> TaskFuture<T::Output> {
+    self.schedule_by(T::find_preferred_target, task).downcast()
+  }
+
+  fn schedule_by(&self, pick: PickTargets, mut task: Task) -> TaskFuture<TaskOutput> {
     // Find all available targets
-    let target_repo = self.repos.iter()
-      .find(|&x| x.has_ownership(task.uuid)).unwrap();
+    let index = match self.repos.iter().position(|x| x.has_ownership(task.uuid)) {
+      Some(index) => index,
+      None => {
+        let (completion, future) = Completion::new(task.uuid);
+        completion.complete(Err(TaskError::UnknownTask(task.uuid)));
+        return future;
+      },
+    };
 
     // Find preferred targets.
-    let targets = T::find_preferred_target(&target_repo.get_schedule_config());
+    let schedule = self.repos[index].get_schedule_config();
+    let targets = pick(&schedule, &self.eligible[index], &task);
+    task.execution_targets = Some(targets);
+    task.priority = task.priority.or(Some(schedule.priority));
+
+    self.route(task)
+  }
+
+  /// Fails with `TaskError::UnknownTask` if no repo owns the task, checked before a timer is added.
+  fn check_owner(&self, task: &Task) -> Result<(), TaskError> {
+    if self.repos.iter().any(|repo| repo.has_ownership(task.uuid)) {
+      Ok(())
+    } else {
+      Err(TaskError::UnknownTask(task.uuid))
+    }
+  }
+
+  /// Schedules the task like `schedule` once the instant passed, see `fire_timers`.
+  pub fn schedule_at<T: ScheduleStrategy + TaskSignature>(&self, task: Task, instant: Instant) -> Result<TimerHandle, TaskError> {
+    self.check_owner(&task)?;
+    Ok(self.timers.add(instant, None, task, T::find_preferred_target))
+  }
+
+  /// Schedules the task like `schedule` once the delay passed, see `fire_timers`.
+  pub fn schedule_after<T: ScheduleStrategy + TaskSignature>(&self, task: Task, delay: Duration) -> Result<TimerHandle, TaskError> {
+    self.schedule_at::<T>(task, self.timers.now() + delay)
+  }
+
+  /// Schedules the task like `schedule` every interval, starting one interval from now.
+  /// Runs that were missed, e.g. because the timers fired late, are skipped.
+  pub fn schedule_every<T: ScheduleStrategy + TaskSignature>(&self, task: Task, interval: Duration) -> Result<TimerHandle, TaskError> {
+    if interval == Duration::from_secs(0) {
+      return Err(TaskError::ZeroInterval(task.uuid));
+    }
+    self.check_owner(&task)?;
+    Ok(self.timers.add(self.timers.now() + interval, Some(interval), task, T::find_preferred_target))
+  }
+
+  /// Schedules the tasks of all due timers, returns their number. Called by the timer
+  /// thread, or directly when the timers use a clock that is advanced manually.
+  /// Tasks no repo owns are skipped, so one bad timer can't stop the others.
+  pub fn fire_timers(&self) -> usize {
+    let mut fired = 0;
+    for (task, pick) in self.timers.take_due() {
+      if self.check_owner(&task).is_ok() {
+        self.schedule_by(pick, task);
+        fired += 1;
+      }
+    }
+    fired
+  }
+
+  /// Number of delayed and periodic tasks that were not cancelled.
+  pub fn pending_timers(&self) -> usize {
+    self.timers.len()
+  }
+
+  /// Spawns the thread that fires the timers once they are due.
+  pub fn spawn_timers(&self) {
+    let context = self.clone();
+    thread::Builder::new()
+      .name("omnidux-timers".to_string())
+      .spawn(move || {
+        loop {
+          context.fire_timers();
+          context.timers.wait();
+        }
+      })
+      .unwrap();
+  }
+
+  /// Sends the task to the given threads, bypassing the schedule strategy of the task.
+  /// If several threads handle the task, the future resolves with the first output.
+  pub fn schedule_on(&self, mut task: Task, targets: Vec<usize>) -> TaskFuture<TaskOutput> {
     task.execution_targets = Some(targets);
+    if task.priority.is_none() {
+      task.priority = self.repos.iter()
+        .find(|repo| repo.has_ownership(task.uuid))
+        .map(|repo| repo.get_schedule_config().priority);
+    }
+
+    self.route(task)
+  }
+
+  /// Delivers the task to the senders of its execution targets only, each target
+  /// receives it once. If a target is no known thread the task isn't delivered at all
+  /// and the future resolves with `TaskError::UnknownThread`.
+  fn route(&self, mut task: Task) -> TaskFuture<TaskOutput> {
+    let (completion, future) = Completion::new(task.uuid);
+    task.completion = Some(completion.clone());
+
+    let targets = task.execution_targets.as_ref().unwrap();
 
-    // Spread tasks using set senders.
-    for sender in self.senders.iter() {
-      sender.send_task(task.clone());
+    let senders = targets.iter()
+      .map(|&target| match self.routes.get(target) {
+        Some(Some(sender)) => Ok((target, sender)),
+        _ => Err(TaskError::UnknownThread { task: task.uuid, thread: target }),
+      })
+      .collect::<Result<Vec<_>, _>>();
+    let senders = match senders {
+      Ok(senders) => senders,
+      Err(error) => {
+        completion.complete(Err(error));
+        return future;
+      },
+    };
+
+    for (i, &(target, sender)) in senders.iter().enumerate() {
+      if !senders[..i].iter().any(|&(other, _)| other == target) {
+        sender.send_task(task.clone());
+      }
     }
+
+    future
+  }
+
+  /// Threads the repo owning the given uuid may be scheduled on.
+  pub fn get_eligible_threads(&self, uuid: usize) -> &EligibleThreads {
+    let index = self.repos.iter()
+      .position(|x| x.has_ownership(uuid)).unwrap();
+    &self.eligible[index]
+  }
+
+  /// Scale of the repo owning the given uuid, resolved when the context was created.
+  pub fn get_scale(&self, uuid: usize) -> ResolvedScale {
+    let index = self.repos.iter()
+      .position(|x| x.has_ownership(uuid)).unwrap();
+    self.scales[index]
   }
 
   pub fn get_repo(&self, uuid: usize) -> &Arc<dyn Repository + Send + Sync> {
@@ -1,22 +1,67 @@
 pub mod context;
 pub mod strategy;
+pub mod timer;
 
+/// Schedules a task of a repo, the payload has to be of the `Input` type of the
+/// task handler, which is checked at compile time. Tasks without payload take `()`.
+/// A trailing `key = ...` sets the key of the task, see `Task::with_key`.
+/// Evaluates to a future of the handler output, see `Context::schedule`.
 #[macro_export]
 macro_rules! schedule_task {
   ($context:ident, $repo:ident, $task:ident) => {
+    schedule_task!($context, $repo, $task, ())
+  };
+  ($context:ident, $repo:ident, $task:ident, $payload:expr) => {
+    $context.schedule::<$repo::$task>(schedule_task!(@task $repo, $task, $payload))
+  };
+  ($context:ident, $repo:ident, $task:ident, $payload:expr, key = $key:expr) => {
+    $context.schedule::<$repo::$task>(schedule_task!(@task $repo, $task, $payload).with_key(&$key))
+  };
+  (@task $repo:ident, $task:ident, $payload:expr) => {
     {
       use std::sync::Arc;
-      use omnidux_core::task::LocalSchedulable;
+      use omnidux_core::task::{LocalSchedulable, TaskSignature};
 
       // Safe because uuid is only set once in its ifetime.
       let repo_uuid = unsafe { $repo::uuid };
       let uuid = repo_uuid + $repo::$task::get_local_handler_uuid();
+      let payload: <$repo::$task as TaskSignature>::Input = $payload;
 
-      $context.schedule::<$repo::$task>(Task {
+      omnidux_core::task::Task {
         execution_targets: None,
         uuid: uuid,
-        payload: Arc::new(Some(0usize)),
-      });
+        payload: Arc::new(payload),
+        key: None,
+        priority: None,
+        completion: None,
+      }
     }
   };
 }
+
+/// Schedules a task of a repo like `schedule_task!` once the instant passed, the payload
+/// is required. Evaluates to a `Result` with the handle of the timer, see `Context::schedule_at`.
+#[macro_export]
+macro_rules! schedule_task_at {
+  ($context:ident, $repo:ident, $task:ident, $payload:expr, $instant:expr) => {
+    $context.schedule_at::<$repo::$task>(schedule_task!(@task $repo, $task, $payload), $instant)
+  };
+}
+
+/// Schedules a task of a repo like `schedule_task!` once the delay passed, see
+/// `Context::schedule_after`.
+#[macro_export]
+macro_rules! schedule_task_after {
+  ($context:ident, $repo:ident, $task:ident, $payload:expr, $delay:expr) => {
+    $context.schedule_after::<$repo::$task>(schedule_task!(@task $repo, $task, $payload), $delay)
+  };
+}
+
+/// Schedules a task of a repo like `schedule_task!` every interval, see
+/// `Context::schedule_every`.
+#[macro_export]
+macro_rules! schedule_task_every {
+  ($context:ident, $repo:ident, $task:ident, $payload:expr, $interval:expr) => {
+    $context.schedule_every::<$repo::$task>(schedule_task!(@task $repo, $task, $payload), $interval)
+  };
+}
@@ -1,4 +1,115 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
 use crate::config::Schedule;
+use crate::task::Task;
+
+/// A thread a task is allowed to run on.
+#[derive(Debug, Clone)]
+pub struct EligibleThread {
+  pub uuid: usize,
+  pub name: String,
+  /// Tasks sent to the thread that it did not handle yet.
+  pub pending: Arc<AtomicUsize>,
+}
+
+impl EligibleThread {
+  /// Number of tasks waiting in the queue of the thread.
+  pub fn queue_depth(&self) -> usize {
+    self.pending.load(Ordering::SeqCst)
+  }
+}
+
+/// Threads selected by the `target` of a repo's schedule, with their names mapped
+/// to the uuids tasks are routed by. Ordered like the threads of the configuration.
+#[derive(Debug, Clone, Default)]
+pub struct EligibleThreads {
+  threads: Vec<EligibleThread>,
+}
+
+impl EligibleThreads {
+  pub fn new(threads: Vec<EligibleThread>) -> Self {
+    EligibleThreads {
+      threads: threads,
+    }
+  }
+
+  pub fn first(&self) -> Option<&EligibleThread> {
+    self.threads.first()
+  }
+
+  /// Finds an eligible thread by its configured name.
+  pub fn find(&self, name: &str) -> Option<&EligibleThread> {
+    self.threads.iter().find(|thread| thread.name == name)
+  }
+
+  pub fn uuids(&self) -> Vec<usize> {
+    self.threads.iter().map(|thread| thread.uuid).collect()
+  }
+
+  pub fn iter(&self) -> std::slice::Iter<'_, EligibleThread> {
+    self.threads.iter()
+  }
+
+  pub fn len(&self) -> usize {
+    self.threads.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.threads.is_empty()
+  }
+}
+
+/// State a strategy keeps across the tasks it schedules, every task type that
+/// implements a strategy using `impl_strategy!` has its own.
+#[derive(Debug, Default)]
+pub struct StrategyState {
+  /// Number of tasks scheduled so far.
+  counter: AtomicUsize,
+  /// State of the random number generator, `0` until it is seeded.
+  random: AtomicU64,
+}
+
+impl StrategyState {
+  pub const fn new() -> Self {
+    StrategyState {
+      counter: AtomicUsize::new(0),
+      random: AtomicU64::new(0),
+    }
+  }
+
+  /// Returns the number of previous calls.
+  pub fn next_count(&self) -> usize {
+    self.counter.fetch_add(1, Ordering::SeqCst)
+  }
+
+  /// Seeds the random number generator, making `random` deterministic.
+  pub fn seed(&self, seed: u64) {
+    self.random.store(seed | 1, Ordering::SeqCst);
+  }
+
+  /// Next number of the random number generator (xorshift), seeded from the clock
+  /// unless it was seeded explicitly.
+  pub fn next_random(&self) -> u64 {
+    if self.random.load(Ordering::SeqCst) == 0 {
+      let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.subsec_nanos()).unwrap_or(0);
+      let _ = self.random.compare_exchange(0, u64::from(nanos) | 1, Ordering::SeqCst, Ordering::SeqCst);
+    }
+
+    let mut current = self.random.load(Ordering::SeqCst);
+    loop {
+      let mut next = current;
+      next ^= next << 13;
+      next ^= next >> 7;
+      next ^= next << 17;
+      match self.random.compare_exchange(current, next, Ordering::SeqCst, Ordering::SeqCst) {
+        Ok(_) => return next,
+        Err(actual) => current = actual,
+      }
+    }
+  }
+}
 
 /// Trait that is required to be implement on each task
 /// The module `omnidux_core::scheduler::strategy` contains multiple
@@ -7,24 +118,82 @@ use crate::config::Schedule;
 /// 
 /// The schedule strategy defines where a task is supposed to be executed.
 /// The strategy implementation gets the schedule configuration of the owning repo
-/// and is then able to decide on which threads the task is executed.
+/// together with the threads its target resolved to and is then able to decide on
+/// which of them the task is executed.
 pub trait ScheduleStrategy {
-  fn find_preferred_target (schedule: &Schedule) -> Vec<usize>; 
+  fn find_preferred_target (schedule: &Schedule, threads: &EligibleThreads, task: &Task) -> Vec<usize>; 
+
+  /// State of the strategy that is shared by all tasks of this type.
+  fn strategy_state() -> &'static StrategyState;
 }
 
 /// Schedule strategy that will always schedule the task on the first available
 /// thread.
-pub fn take_first(_schedule: &Schedule) -> Vec<usize> {
-  vec![0]
+pub fn take_first(_schedule: &Schedule, threads: &EligibleThreads, _task: &Task, _state: &StrategyState) -> Vec<usize> {
+  threads.first().map(|thread| vec![thread.uuid]).unwrap_or_default()
+}
+
+/// Schedule strategy that takes turns between the available threads, the turns are
+/// counted per task type.
+pub fn round_robin(_schedule: &Schedule, threads: &EligibleThreads, _task: &Task, state: &StrategyState) -> Vec<usize> {
+  if threads.is_empty() {
+    return Vec::new();
+  }
+
+  vec![threads.threads[state.next_count() % threads.len()].uuid]
+}
+
+/// Schedule strategy that picks the available thread with the fewest pending tasks,
+/// preferring the first one on ties.
+pub fn least_loaded(_schedule: &Schedule, threads: &EligibleThreads, _task: &Task, _state: &StrategyState) -> Vec<usize> {
+  threads.iter()
+    .enumerate()
+    .min_by_key(|(i, thread)| (thread.queue_depth(), *i))
+    .map(|(_, thread)| vec![thread.uuid])
+    .unwrap_or_default()
+}
+
+/// Schedule strategy that picks a random available thread, use `StrategyState::seed`
+/// for reproducible schedules.
+pub fn random(_schedule: &Schedule, threads: &EligibleThreads, _task: &Task, state: &StrategyState) -> Vec<usize> {
+  if threads.is_empty() {
+    return Vec::new();
+  }
+
+  vec![threads.threads[(state.next_random() % threads.len() as u64) as usize].uuid]
+}
+
+/// Schedule strategy that always picks the same available thread for tasks with the
+/// same key, see `Task::with_key`. Tasks without a key run on the first thread.
+pub fn key_affinity(_schedule: &Schedule, threads: &EligibleThreads, task: &Task, _state: &StrategyState) -> Vec<usize> {
+  match task.key {
+    Some(key) if !threads.is_empty() => vec![threads.threads[(key % threads.len() as u64) as usize].uuid],
+    _ => threads.first().map(|thread| vec![thread.uuid]).unwrap_or_default(),
+  }
+}
+
+/// Schedule strategy that runs the task on every available thread.
+pub fn broadcast(_schedule: &Schedule, threads: &EligibleThreads, _task: &Task, _state: &StrategyState) -> Vec<usize> {
+  threads.uuids()
 }
 
 #[macro_export]
 macro_rules! impl_strategy {
   ($task:ident, $fn:ident) => {
     impl ScheduleStrategy for $task {
-      fn find_preferred_target (schedule: &omnidux_core::config::Schedule) -> Vec<usize> {
-        $fn(schedule)
-      } 
+      fn find_preferred_target (
+        schedule: &omnidux_core::config::Schedule,
+        threads: &omnidux_core::scheduler::strategy::EligibleThreads,
+        task: &omnidux_core::task::Task,
+      ) -> Vec<usize> {
+        $fn(schedule, threads, task, Self::strategy_state())
+      }
+
+      fn strategy_state() -> &'static omnidux_core::scheduler::strategy::StrategyState {
+        static STATE: omnidux_core::scheduler::strategy::StrategyState =
+          omnidux_core::scheduler::strategy::StrategyState::new();
+        &STATE
+      }
     }
   };
 }
@@ -0,0 +1,214 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::sync::{Arc, Condvar, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
+use std::time::{Duration, Instant};
+
+use crate::config::Schedule;
+use crate::scheduler::strategy::EligibleThreads;
+use crate::task::Task;
+
+/// Picks the threads of a task, the `find_preferred_target` of its strategy.
+pub type PickTargets = fn(&Schedule, &EligibleThreads, &Task) -> Vec<usize>;
+
+/// Source of the current time for the timer service.
+pub trait Clock: Send + Sync {
+  fn now(&self) -> Instant;
+}
+
+/// The monotonic clock of the system.
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+  fn now(&self) -> Instant {
+    Instant::now()
+  }
+}
+
+/// A clock that only moves when it is advanced, used to test timers without waiting.
+pub struct ManualClock {
+  now: Mutex<Instant>,
+}
+
+impl ManualClock {
+  pub fn new() -> Self {
+    ManualClock {
+      now: Mutex::new(Instant::now()),
+    }
+  }
+
+  pub fn advance(&self, duration: Duration) {
+    *self.now.lock().unwrap() += duration;
+  }
+}
+
+impl Default for ManualClock {
+  fn default() -> Self {
+    ManualClock::new()
+  }
+}
+
+impl Clock for ManualClock {
+  fn now(&self) -> Instant {
+    *self.now.lock().unwrap()
+  }
+}
+
+/// Cancels a delayed or periodic task. Dropping the handle keeps the timer running.
+#[derive(Debug, Clone)]
+pub struct TimerHandle {
+  cancelled: Arc<AtomicBool>,
+}
+
+impl TimerHandle {
+  /// Stops the timer, a task that was already scheduled is still handled.
+  pub fn cancel(&self) {
+    self.cancelled.store(true, AtomicOrdering::SeqCst);
+  }
+
+  pub fn is_cancelled(&self) -> bool {
+    self.cancelled.load(AtomicOrdering::SeqCst)
+  }
+}
+
+struct Timer {
+  due: Instant,
+  /// Order of creation, timers that are due at the same time fire in this order.
+  id: u64,
+  /// Set for periodic timers.
+  interval: Option<Duration>,
+  task: Task,
+  pick: PickTargets,
+  cancelled: Arc<AtomicBool>,
+}
+
+impl PartialEq for Timer {
+  fn eq(&self, other: &Timer) -> bool {
+    self.cmp(other) == Ordering::Equal
+  }
+}
+
+impl Eq for Timer {}
+
+impl PartialOrd for Timer {
+  fn partial_cmp(&self, other: &Timer) -> Option<Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+impl Ord for Timer {
+  /// Reversed, so the heap returns the timer that is due first.
+  fn cmp(&self, other: &Timer) -> Ordering {
+    (other.due, other.id).cmp(&(self.due, self.id))
+  }
+}
+
+#[derive(Default)]
+struct Timers {
+  heap: BinaryHeap<Timer>,
+  next_id: u64,
+}
+
+/// Holds the delayed and periodic tasks of a context until they are due, they are
+/// then scheduled like any other task, see `Context::fire_timers`.
+pub struct TimerService {
+  clock: Arc<dyn Clock>,
+  timers: Mutex<Timers>,
+  /// Notified whenever a timer was added, so the timer thread can recompute its wait.
+  changed: Condvar,
+}
+
+impl TimerService {
+  pub fn new(clock: Arc<dyn Clock>) -> Self {
+    TimerService {
+      clock: clock,
+      timers: Mutex::new(Timers::default()),
+      changed: Condvar::new(),
+    }
+  }
+
+  pub fn now(&self) -> Instant {
+    self.clock.now()
+  }
+
+  /// Adds a timer that schedules the task once it is due and then every interval.
+  pub fn add(&self, due: Instant, interval: Option<Duration>, task: Task, pick: PickTargets) -> TimerHandle {
+    let cancelled = Arc::new(AtomicBool::new(false));
+
+    let mut timers = self.timers.lock().unwrap();
+    let id = timers.next_id;
+    timers.next_id += 1;
+    timers.heap.push(Timer {
+      due: due,
+      id: id,
+      interval: interval,
+      task: task,
+      pick: pick,
+      cancelled: cancelled.clone(),
+    });
+    self.changed.notify_all();
+
+    TimerHandle {
+      cancelled: cancelled,
+    }
+  }
+
+  /// Removes the timers that are due and returns their tasks. Periodic timers are
+  /// added again for their next run, runs that were missed are skipped.
+  pub fn take_due(&self) -> Vec<(Task, PickTargets)> {
+    let now = self.clock.now();
+    let mut timers = self.timers.lock().unwrap();
+
+    let mut due = Vec::new();
+    let mut periodic = Vec::new();
+    while timers.heap.peek().map(|timer| timer.due <= now).unwrap_or(false) {
+      let mut timer = timers.heap.pop().unwrap();
+      if timer.cancelled.load(AtomicOrdering::SeqCst) {
+        continue;
+      }
+
+      match timer.interval {
+        Some(interval) => {
+          due.push((timer.task.clone(), timer.pick));
+          while timer.due <= now {
+            timer.due += interval;
+          }
+          periodic.push(timer);
+        },
+        None => due.push((timer.task, timer.pick)),
+      }
+    }
+
+    timers.heap.extend(periodic);
+    due
+  }
+
+  /// Number of timers that were not cancelled yet.
+  pub fn len(&self) -> usize {
+    self.timers.lock().unwrap().heap.iter()
+      .filter(|timer| !timer.cancelled.load(AtomicOrdering::SeqCst))
+      .count()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+
+  /// Blocks until the next timer is due or a timer was added.
+  pub fn wait(&self) {
+    let timers = self.timers.lock().unwrap();
+    let next = timers.heap.peek().map(|timer| timer.due);
+
+    match next {
+      Some(due) => {
+        let now = self.clock.now();
+        if due > now {
+          drop(self.changed.wait_timeout(timers, due - now).unwrap());
+        }
+      },
+      None => {
+        drop(self.changed.wait(timers).unwrap());
+      },
+    }
+  }
+}
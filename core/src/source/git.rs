@@ -0,0 +1,61 @@
+use std::path::Path;
+use std::process::Command;
+
+use super::SourceError;
+
+/// Runs a git command and returns its trimmed standard output.
+pub fn git(directory: Option<&Path>, args: &[&str]) -> Result<String, SourceError> {
+  let mut command = Command::new("git");
+  if let Some(directory) = directory {
+    command.arg("-C").arg(directory);
+  }
+
+  let output = command.args(args).output()?;
+  if !output.status.success() {
+    return Err(SourceError::Git {
+      command: format!("git {}", args.join(" ")),
+      message: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+    });
+  }
+
+  Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Clones the repository into the checkout folder, or fetches the latest
+/// changes if it has been cloned before.
+pub fn clone_or_fetch(url: &str, checkout: &Path) -> Result<(), SourceError> {
+  if checkout.join(".git").exists() {
+    git(Some(checkout), &["remote", "set-url", "--", "origin", url])?;
+    git(Some(checkout), &["fetch", "--quiet", "--tags", "--force", "origin"])?;
+  } else {
+    let checkout = checkout.to_string_lossy();
+    git(None, &["clone", "--quiet", "--no-checkout", "--", url, &checkout])?;
+  }
+
+  Ok(())
+}
+
+/// Resolves a revision, branch or tag of the fetched repository into a commit hash.
+pub fn resolve_commit(checkout: &Path, reference: &str) -> Result<String, SourceError> {
+  // Git would read a leading `-` as an option.
+  if reference.starts_with('-') {
+    return Err(SourceError::InvalidReference(reference.to_string()));
+  }
+
+  git(Some(checkout), &["rev-parse", "--verify", "--quiet", &format!("{}^{{commit}}", reference)])
+}
+
+/// Whether the value is a full or abbreviated commit hash.
+pub fn is_commit_hash(value: &str) -> bool {
+  !value.is_empty() && value.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Checks out the given commit, discarding any local changes.
+pub fn checkout_commit(checkout: &Path, commit: &str) -> Result<(), SourceError> {
+  if !is_commit_hash(commit) {
+    return Err(SourceError::InvalidReference(commit.to_string()));
+  }
+
+  git(Some(checkout), &["checkout", "--quiet", "--force", "--detach", commit])?;
+  Ok(())
+}
@@ -0,0 +1,64 @@
+use std::fs;
+use std::path::Path;
+
+use serde::{Serialize, Deserialize};
+
+use super::SourceError;
+
+/// Name of the lockfile, next to the platform configuration.
+pub const LOCK_FILE: &str = "omnidux.lock";
+
+/// Resolved commit of a single git repo.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LockedRepo {
+  pub name: String,
+  pub url: String,
+  /// The requested reference, e.g. `branch:main`, the locked commit is only reused
+  /// as long as url and reference don't change.
+  pub reference: String,
+  pub commit: String,
+}
+
+/// Contents of the lockfile.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Lockfile {
+  #[serde(default)]
+  pub repos: Vec<LockedRepo>,
+}
+
+impl Lockfile {
+  /// Reads the lockfile from the project folder, a missing lockfile is empty.
+  pub fn read(project: &Path) -> Result<Lockfile, SourceError> {
+    let file = project.join(LOCK_FILE);
+    if !file.exists() {
+      return Ok(Default::default());
+    }
+
+    let content = fs::read_to_string(file)?;
+    Ok(serde_yaml::from_str(&content)?)
+  }
+
+  /// Writes the lockfile into the project folder, sorted by repo name.
+  pub fn write(&self, project: &Path) -> Result<(), SourceError> {
+    let mut lockfile = self.clone();
+    lockfile.repos.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let content = format!(
+      "# This file is generated by omnidux, do not edit it manually.\n{}\n",
+      serde_yaml::to_string(&lockfile)?,
+    );
+    fs::write(project.join(LOCK_FILE), content)?;
+    Ok(())
+  }
+
+  /// Returns the locked entry of a repo if url and reference are still the same.
+  pub fn find(&self, name: &str, url: &str, reference: &str) -> Option<&LockedRepo> {
+    self.repos.iter().find(|repo| repo.name == name && repo.url == url && repo.reference == reference)
+  }
+
+  /// Inserts or replaces the locked entry of a repo.
+  pub fn insert(&mut self, locked: LockedRepo) {
+    self.repos.retain(|repo| repo.name != locked.name);
+    self.repos.push(locked);
+  }
+}
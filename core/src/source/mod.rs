@@ -0,0 +1,187 @@
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::{Configuration, Repo, RepoType};
+
+pub use crate::config::CHECKOUT_FOLDER;
+
+pub mod git;
+pub mod lock;
+
+pub use lock::{Lockfile, LockedRepo, LOCK_FILE};
+
+#[derive(Debug)]
+pub enum SourceError {
+  IOError (std::io::Error),
+  LockError (serde_yaml::Error),
+  Git { command: String, message: String },
+  UnknownReference { repo: String, reference: String },
+  /// A reference or locked commit that git would read as an option, or that is no commit hash.
+  InvalidReference (String),
+}
+
+impl fmt::Display for SourceError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      SourceError::IOError(error) => write!(f, "Failed to access repo source: {}", error),
+      SourceError::LockError(error) => write!(f, "Failed to read or write {}: {}", LOCK_FILE, error),
+      SourceError::Git { command, message } => write!(f, "`{}` failed: {}", command, message),
+      SourceError::UnknownReference { repo, reference } => {
+        write!(f, "Repo `{}` has no commit for reference `{}`", repo, reference)
+      },
+      SourceError::InvalidReference(reference) => write!(f, "`{}` is not a valid git reference", reference),
+    }
+  }
+}
+
+impl std::error::Error for SourceError {}
+
+impl From<std::io::Error> for SourceError {
+  fn from(error: std::io::Error) -> Self {
+    SourceError::IOError(error)
+  }
+}
+
+impl From<serde_yaml::Error> for SourceError {
+  fn from(error: serde_yaml::Error) -> Self {
+    SourceError::LockError(error)
+  }
+}
+
+/// A repo whose sources are available on disk.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolvedRepo {
+  /// Name as referenced by the configuration.
+  pub name: String,
+  /// Folder of the repo sources, relative to the project folder.
+  pub path: PathBuf,
+  /// Commit of the checkout, only set for git repos.
+  pub commit: Option<String>,
+}
+
+/// Describes the requested reference of a git repo, as written to the lockfile.
+fn describe_reference(rev: &Option<String>, branch: &Option<String>, tag: &Option<String>) -> (String, String) {
+  match (rev, branch, tag) {
+    (Some(rev), _, _) => (format!("rev:{}", rev), rev.clone()),
+    (_, Some(branch), _) => (format!("branch:{}", branch), format!("origin/{}", branch)),
+    (_, _, Some(tag)) => (format!("tag:{}", tag), format!("refs/tags/{}", tag)),
+    _ => ("HEAD".to_string(), "origin/HEAD".to_string()),
+  }
+}
+
+/// Resolves a single repo, git repos are checked out at their locked commit unless
+/// `update` is set or the lockfile doesn't match the configuration anymore.
+fn resolve_repo(project: &Path, repo: &Repo, lockfile: &mut Lockfile, update: bool) -> Result<ResolvedRepo, SourceError> {
+  let name = repo.name();
+
+  let (url, rev, branch, tag) = match &repo.repo_type {
+    RepoType::Local => {
+      return Ok(ResolvedRepo {
+        name: name,
+        path: PathBuf::from(&repo.source),
+        commit: None,
+      });
+    },
+    RepoType::Git { url, rev, branch, tag } => (url, rev, branch, tag),
+  };
+
+  let checkout = project.join(CHECKOUT_FOLDER).join(&name);
+  fs::create_dir_all(project.join(CHECKOUT_FOLDER))?;
+
+  let (reference, revision) = describe_reference(rev, branch, tag);
+  let locked = lockfile.find(&name, url, &reference).map(|locked| locked.commit.clone());
+
+  // A locked commit that is already checked out doesn't need any network access.
+  let current = if checkout.join(".git").exists() {
+    git::git(Some(&checkout), &["rev-parse", "HEAD"]).ok()
+  } else {
+    None
+  };
+  let commit = match locked {
+    Some(commit) if !update && current.as_ref() == Some(&commit) => commit,
+    Some(commit) if !update => {
+      git::clone_or_fetch(url, &checkout)?;
+      commit
+    },
+    _ => {
+      git::clone_or_fetch(url, &checkout)?;
+      git::resolve_commit(&checkout, &revision).map_err(|error| match error {
+        SourceError::InvalidReference(_) => error,
+        _ => SourceError::UnknownReference { repo: name.clone(), reference: reference.clone() },
+      })?
+    },
+  };
+
+  if current.as_ref() != Some(&commit) {
+    git::checkout_commit(&checkout, &commit)?;
+  }
+
+  lockfile.insert(LockedRepo {
+    name: name.clone(),
+    url: url.clone(),
+    reference: reference,
+    commit: commit.clone(),
+  });
+
+  Ok(ResolvedRepo {
+    name: name,
+    path: repo.path(),
+    commit: Some(commit),
+  })
+}
+
+/// Makes the sources of all configured repos available and updates the lockfile
+/// inside of the project folder.
+pub fn resolve_repos(config: &Configuration, project: &Path, update: bool) -> Result<Vec<ResolvedRepo>, SourceError> {
+  let mut lockfile = Lockfile::read(project)?;
+
+  let resolved = config.repos.iter()
+    .map(|repo| resolve_repo(project, repo, &mut lockfile, update))
+    .collect::<Result<Vec<_>, _>>()?;
+
+  // Drop entries of repos that are no longer configured.
+  lockfile.repos.retain(|locked| resolved.iter().any(|repo| repo.name == locked.name && repo.commit.is_some()));
+  if !lockfile.repos.is_empty() || project.join(LOCK_FILE).exists() {
+    lockfile.write(project)?;
+  }
+
+  Ok(resolved)
+}
+
+/// Describes the repos like `resolve_repos` without any side effect: nothing is fetched,
+/// checked out or written. Git repos resolve to their locked commit, or to the commit
+/// their existing checkout is at, so dry runs generate against the sources on disk.
+pub fn plan_repos(config: &Configuration, project: &Path) -> Result<Vec<ResolvedRepo>, SourceError> {
+  let lockfile = Lockfile::read(project)?;
+
+  Ok(config.repos.iter().map(|repo| {
+    let name = repo.name();
+    let commit = match &repo.repo_type {
+      RepoType::Local => {
+        return ResolvedRepo {
+          name: name,
+          path: PathBuf::from(&repo.source),
+          commit: None,
+        };
+      },
+      RepoType::Git { url, rev, branch, tag } => {
+        let (reference, _) = describe_reference(rev, branch, tag);
+        let checkout = project.join(CHECKOUT_FOLDER).join(&name);
+        lockfile.find(&name, url, &reference)
+          .map(|locked| locked.commit.clone())
+          .or_else(|| if checkout.join(".git").exists() {
+            git::git(Some(&checkout), &["rev-parse", "HEAD"]).ok()
+          } else {
+            None
+          })
+      },
+    };
+
+    ResolvedRepo {
+      name: name,
+      path: repo.path(),
+      commit: commit,
+    }
+  }).collect())
+}
@@ -0,0 +1,100 @@
+use std::any::Any;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+use futures::channel::oneshot;
+use futures::executor::block_on;
+
+use super::TaskError;
+
+/// Output of a task handler with an erased type.
+pub type TaskOutput = Box<dyn Any + Send>;
+
+type TaskResult = Result<TaskOutput, TaskError>;
+
+/// Sending half of a task result, shared by all clones of the task. Only the
+/// first thread that handles the task completes it.
+#[derive(Clone)]
+pub struct Completion {
+  sender: Arc<Mutex<Option<oneshot::Sender<TaskResult>>>>,
+}
+
+impl Completion {
+  /// Creates a completion and the future that resolves once it was completed.
+  pub fn new(task: usize) -> (Completion, TaskFuture<TaskOutput>) {
+    let (sender, receiver) = oneshot::channel();
+    let completion = Completion {
+      sender: Arc::new(Mutex::new(Some(sender))),
+    };
+
+    (completion, TaskFuture {
+      task: task,
+      receiver: receiver,
+      convert: erased_output,
+    })
+  }
+
+  /// Resolves the future with the result, later results are dropped.
+  pub fn complete(&self, result: TaskResult) {
+    if let Some(sender) = self.sender.lock().unwrap().take() {
+      // The future may have been dropped, the result is not needed then.
+      let _ = sender.send(result);
+    }
+  }
+}
+
+/// Resolves with the output of a scheduled task, or with the error that
+/// prevented the handler from producing it.
+pub struct TaskFuture<T> {
+  /// Uuid of the task handler.
+  task: usize,
+  receiver: oneshot::Receiver<TaskResult>,
+  convert: fn(usize, TaskOutput) -> Result<T, TaskError>,
+}
+
+impl<T> TaskFuture<T> {
+  /// Blocks the current thread until the task was handled, must not be called
+  /// on the thread the task is handled on.
+  pub fn wait(self) -> Result<T, TaskError> {
+    block_on(self)
+  }
+}
+
+impl TaskFuture<TaskOutput> {
+  /// Turns the future into one that resolves with the output of the given type.
+  pub fn downcast<T: Any>(self) -> TaskFuture<T> {
+    TaskFuture {
+      task: self.task,
+      receiver: self.receiver,
+      convert: typed_output::<T>,
+    }
+  }
+}
+
+fn erased_output(_task: usize, output: TaskOutput) -> Result<TaskOutput, TaskError> {
+  Ok(output)
+}
+
+fn typed_output<T: Any>(task: usize, output: TaskOutput) -> Result<T, TaskError> {
+  output.downcast::<T>().map(|output| *output).map_err(|_| TaskError::OutputMismatch {
+    task: task,
+    expected: std::any::type_name::<T>(),
+  })
+}
+
+impl<T> Future for TaskFuture<T> {
+  type Output = Result<T, TaskError>;
+
+  fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+    let future = self.get_mut();
+    match Pin::new(&mut future.receiver).poll(cx) {
+      Poll::Ready(Ok(Ok(output))) => Poll::Ready((future.convert)(future.task, output)),
+      Poll::Ready(Ok(Err(error))) => Poll::Ready(Err(error)),
+      // The completion was dropped without a result, e.g. because no thread received the task.
+      Poll::Ready(Err(_)) => Poll::Ready(Err(TaskError::Dropped(future.task))),
+      Poll::Pending => Poll::Pending,
+    }
+  }
+}
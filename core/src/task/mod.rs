@@ -1,5 +1,17 @@
 use std::sync::Arc;
 use std::any::Any;
+use std::collections::hash_map::DefaultHasher;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::panic::{self, AssertUnwindSafe};
+
+use futures::future::{BoxFuture, FutureExt};
+
+use crate::config::TaskPriority;
+
+pub mod completion;
+
+use completion::{Completion, TaskOutput};
 
 pub type TaskPayload = Arc<dyn Any + Send + Sync>;
 
@@ -12,14 +24,197 @@ pub struct Task {
   pub uuid: usize,
   /// Data that was passed as a argument.
   pub payload: TaskPayload,
+  /// Hash of the entity the task works on, used by the `key_affinity` strategy.
+  pub key: Option<u64>,
+  /// Urgency of the task, the default priority of the repo's schedule is used if unset.
+  pub priority: Option<TaskPriority>,
+  /// Receives the result of the handler, set by the context when the task is scheduled.
+  pub completion: Option<Completion>,
 }
 
+impl Task {
+  /// Sets the key of the task, tasks with the same key are kept on the same thread
+  /// by the `key_affinity` strategy.
+  pub fn with_key<K: Hash>(mut self, key: &K) -> Task {
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    self.key = Some(hasher.finish());
+    self
+  }
+
+  /// Sets the priority of the task, overriding the default priority of the repo.
+  pub fn with_priority(mut self, priority: TaskPriority) -> Task {
+    self.priority = Some(priority);
+    self
+  }
+
+  /// The payload of the task if it is of the given type.
+  pub fn input<T: Any>(&self) -> Option<&T> {
+    self.payload.downcast_ref::<T>()
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TaskError {
+  /// No handler of the repo has the uuid of the task.
+  UnknownTask (usize),
+  /// The payload of the task is not the input type of its handler.
+  PayloadMismatch { task: usize, expected: &'static str },
+  /// The output of the task is not of the type the result was awaited as.
+  OutputMismatch { task: usize, expected: &'static str },
+  /// The handler of the task panicked.
+  Panicked { task: usize, message: String },
+  /// The task was dropped before any thread handled it.
+  Dropped (usize),
+  /// The task was routed to a thread uuid no thread has, it was not delivered to any thread.
+  UnknownThread { task: usize, thread: usize },
+  /// The task was scheduled to repeat every zero interval.
+  ZeroInterval (usize),
+}
+
+impl fmt::Display for TaskError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      TaskError::UnknownTask(uuid) => write!(f, "No task handler with uuid {}", uuid),
+      TaskError::PayloadMismatch { task, expected } => {
+        write!(f, "Task {} expects a payload of type `{}`", task, expected)
+      },
+      TaskError::OutputMismatch { task, expected } => {
+        write!(f, "Task {} does not produce an output of type `{}`", task, expected)
+      },
+      TaskError::Panicked { task, message } => write!(f, "Task {} panicked: {}", task, message),
+      TaskError::Dropped(uuid) => write!(f, "Task {} was dropped before it was handled", uuid),
+      TaskError::UnknownThread { task, thread } => {
+        write!(f, "No thread with uuid {} to route task {} to", thread, task)
+      },
+      TaskError::ZeroInterval(uuid) => write!(f, "Task {} can't repeat every zero interval", uuid),
+    }
+  }
+}
+
+impl std::error::Error for TaskError {}
+
+/// Future returned by an `AsyncTaskHandler`.
+pub type HandlerFuture<T> = BoxFuture<'static, T>;
+
 // Trait for handleable tasks.
 pub trait TaskHandler {
-  fn handle(&self, task: &Task);
+  /// Type of the payload the task is scheduled with, see `schedule_task!`.
+  type Input: Any + Send + Sync;
+  /// Type of the value the task resolves with, see `Context::schedule`.
+  type Output: Any + Send;
+
+  fn handle(&self, task: &Task, input: &Self::Input) -> Self::Output;
+}
+
+/// Task handler whose output is produced by a future. The future is polled by the
+/// thread the task was routed to, which keeps handling other tasks in the meantime.
+/// Tasks with an async handler are registered with `impl_async_task!`.
+pub trait AsyncTaskHandler {
+  /// Type of the payload the task is scheduled with, see `schedule_task!`.
+  type Input: Any + Send + Sync;
+  /// Type of the value the task resolves with, see `Context::schedule`.
+  type Output: Any + Send;
+
+  fn handle(&self, task: &Task, input: Arc<Self::Input>) -> HandlerFuture<Self::Output>;
+}
+
+/// Input and output type of a task, regardless of whether its handler is async.
+pub trait TaskSignature {
+  type Input: Any + Send + Sync;
+  type Output: Any + Send;
+}
+
+impl<T: TaskHandler> TaskSignature for T {
+  type Input = T::Input;
+  type Output = T::Output;
+}
+
+/// Result of a handler, async handlers return the future that produces it.
+pub enum TaskOutcome {
+  Ready (TaskOutput),
+  Pending (HandlerFuture<Result<TaskOutput, TaskError>>),
+}
+
+/// Task handler with an erased input type, implemented for every `TaskHandler`.
+/// Checks the payload of a task before handing it to the typed handler and
+/// catches panics of the handler, so they don't take down the thread.
+pub trait AnyTaskHandler {
+  fn handle_any(&self, task: &Task) -> Result<TaskOutcome, TaskError>;
+}
+
+/// Message of a caught panic, panics with a payload other than a string have none.
+fn panic_message(panic: &(dyn Any + Send)) -> String {
+  if let Some(message) = panic.downcast_ref::<&str>() {
+    message.to_string()
+  } else if let Some(message) = panic.downcast_ref::<String>() {
+    message.clone()
+  } else {
+    "unknown panic payload".to_string()
+  }
+}
+
+fn panicked(task: usize, panic: Box<dyn Any + Send>) -> TaskError {
+  TaskError::Panicked {
+    task: task,
+    message: panic_message(&*panic),
+  }
+}
+
+impl<T: TaskHandler> AnyTaskHandler for T {
+  fn handle_any(&self, task: &Task) -> Result<TaskOutcome, TaskError> {
+    let input = task.input::<T::Input>().ok_or_else(|| TaskError::PayloadMismatch {
+      task: task.uuid,
+      expected: std::any::type_name::<T::Input>(),
+    })?;
+
+    match panic::catch_unwind(AssertUnwindSafe(|| self.handle(task, input))) {
+      Ok(output) => Ok(TaskOutcome::Ready(Box::new(output))),
+      Err(panic) => Err(panicked(task.uuid, panic)),
+    }
+  }
+}
+
+/// Erased handling of a task with an async handler, see `impl_async_task!`.
+/// Panics while creating or polling the future are reported like the ones of
+/// synchronous handlers.
+pub fn handle_async<T: AsyncTaskHandler>(handler: &T, task: &Task) -> Result<TaskOutcome, TaskError> {
+  let input = task.payload.clone().downcast::<T::Input>().map_err(|_| TaskError::PayloadMismatch {
+    task: task.uuid,
+    expected: std::any::type_name::<T::Input>(),
+  })?;
+
+  let uuid = task.uuid;
+  let future = panic::catch_unwind(AssertUnwindSafe(|| handler.handle(task, input)))
+    .map_err(|panic| panicked(uuid, panic))?;
+
+  Ok(TaskOutcome::Pending(AssertUnwindSafe(future).catch_unwind()
+    .map(move |result| match result {
+      Ok(output) => Ok(Box::new(output) as TaskOutput),
+      Err(panic) => Err(panicked(uuid, panic)),
+    })
+    .boxed()))
+}
+
+/// Registers a task whose handler implements `AsyncTaskHandler`.
+#[macro_export]
+macro_rules! impl_async_task {
+  ($task:ident) => {
+    impl omnidux_core::task::AnyTaskHandler for $task {
+      fn handle_any(&self, task: &omnidux_core::task::Task)
+        -> Result<omnidux_core::task::TaskOutcome, omnidux_core::task::TaskError> {
+        omnidux_core::task::handle_async(self, task)
+      }
+    }
+
+    impl omnidux_core::task::TaskSignature for $task {
+      type Input = <$task as omnidux_core::task::AsyncTaskHandler>::Input;
+      type Output = <$task as omnidux_core::task::AsyncTaskHandler>::Output;
+    }
+  };
 }
 
 // Trait for tasks that can be scheduled within a specific repo. 
 pub trait LocalSchedulable {
   fn get_local_handler_uuid() -> usize;
-}
\ No newline at end of file
+}
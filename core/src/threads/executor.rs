@@ -0,0 +1,101 @@
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::Sender;
+use std::task::{Context, Poll, Waker};
+
+use futures::future::BoxFuture;
+use futures::task::{waker, ArcWake};
+
+use crate::task::Task;
+
+/// Message received by a thread.
+pub enum ThreadMessage {
+  /// A task routed to the thread.
+  Task (Task),
+  /// The future with the given id inside of the executor of the thread can make progress.
+  Wake (usize),
+}
+
+/// Wakes a future by queueing a message for the thread that polls it, so wakes are
+/// handled in order with the tasks the thread receives.
+struct ThreadWaker {
+  id: usize,
+  sender: Mutex<Sender<ThreadMessage>>,
+}
+
+impl ArcWake for ThreadWaker {
+  fn wake_by_ref(arc_self: &Arc<Self>) {
+    // The thread is gone if sending fails, there is nothing left to wake.
+    let _ = arc_self.sender.lock().unwrap().send(ThreadMessage::Wake(arc_self.id));
+  }
+}
+
+struct Spawned {
+  future: BoxFuture<'static, ()>,
+  waker: Waker,
+}
+
+/// Polls the futures of async task handlers on the thread they were routed to.
+/// Futures are only polled when they are spawned and after they were woken.
+pub struct Executor {
+  sender: Sender<ThreadMessage>,
+  futures: Vec<Option<Spawned>>,
+  /// Ids of the finished futures, reused by new ones.
+  free: Vec<usize>,
+}
+
+impl Executor {
+  /// Creates an executor whose wakes are sent to the channel of its thread.
+  pub fn new(sender: Sender<ThreadMessage>) -> Self {
+    Executor {
+      sender: sender,
+      futures: Vec::new(),
+      free: Vec::new(),
+    }
+  }
+
+  /// Polls the future once and keeps it until it finished.
+  pub fn spawn(&mut self, future: BoxFuture<'static, ()>) {
+    let id = match self.free.pop() {
+      Some(id) => id,
+      None => {
+        self.futures.push(None);
+        self.futures.len() - 1
+      },
+    };
+
+    let waker = waker(Arc::new(ThreadWaker {
+      id: id,
+      sender: Mutex::new(self.sender.clone()),
+    }));
+    self.futures[id] = Some(Spawned {
+      future: future,
+      waker: waker,
+    });
+    self.wake(id);
+  }
+
+  /// Polls the future with the given id, wakes of finished futures are ignored.
+  pub fn wake(&mut self, id: usize) {
+    let finished = match self.futures.get_mut(id) {
+      Some(Some(spawned)) => {
+        let mut cx = Context::from_waker(&spawned.waker);
+        spawned.future.as_mut().poll(&mut cx) == Poll::Ready(())
+      },
+      _ => false,
+    };
+
+    if finished {
+      self.futures[id] = None;
+      self.free.push(id);
+    }
+  }
+
+  /// Number of futures that have not finished yet.
+  pub fn len(&self) -> usize {
+    self.futures.len() - self.free.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+}
@@ -1,49 +1,130 @@
 use std::thread;
-use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{channel, Receiver, RecvError, Sender, TryRecvError};
+
+use futures::future::FutureExt;
 
 use crate::task::Task;
 use crate::config::Thread as ThreadConfig;
-use crate::config::ThreadType;
+use crate::config::{ThreadDriver, ThreadType};
 use crate::scheduler::context::Context;
 
+pub mod executor;
+pub mod priority;
+
+use executor::{Executor, ThreadMessage};
+use priority::{PriorityQueue, STARVATION_LIMIT};
+
 pub struct Thread {
   /// Task uuid.
   uuid: usize,
   /// Task sender.
-  sender: Sender<Task>,
+  sender: Sender<ThreadMessage>,
   /// Task fulfillment receiver.
-  receiver: Option<Receiver<Task>>,
+  receiver: Option<Inbox>,
+  /// Tasks that were sent but not handled yet, including the ones of in-flight async handlers.
+  pending: Arc<AtomicUsize>,
+  /// Futures of async handlers, used while the thread is polled or blocked.
+  executor: Executor,
   /// Thread configuration.
   config: ThreadConfig,
 }
 
 #[derive(Clone)]
 pub struct ThreadSender {
-  pub sender: Sender<Task>,
+  /// Uuid of the paired thread, tasks targeting it are routed to this sender.
+  pub uuid: usize,
+  /// Configured name of the paired thread.
+  pub name: String,
+  pub sender: Sender<ThreadMessage>,
+  /// Queue depth of the paired thread.
+  pub pending: Arc<AtomicUsize>,
+}
+
+/// Receiving end of a thread, hands out the tasks in the order of the thread driver.
+struct Inbox {
+  receiver: Receiver<ThreadMessage>,
+  /// Received tasks that were not handled yet, only used by the `priority` driver.
+  queue: Option<PriorityQueue>,
+}
+
+impl Inbox {
+  /// Moves all received tasks into the queue and takes the most urgent one, wakes of
+  /// in-flight tasks are returned right away. Fails like `try_recv` once the queue is empty.
+  fn next_queued(receiver: &Receiver<ThreadMessage>, queue: &mut PriorityQueue) -> Result<ThreadMessage, TryRecvError> {
+    let error = loop {
+      match receiver.try_recv() {
+        Ok(ThreadMessage::Task(task)) => queue.push(task),
+        Ok(wake) => return Ok(wake),
+        Err(error) => break error,
+      }
+    };
+    queue.pop().map(ThreadMessage::Task).ok_or(error)
+  }
+
+  /// Every task goes through the queue, even if it is empty, so its count of handled
+  /// tasks stays accurate.
+  fn recv(&mut self) -> Result<ThreadMessage, RecvError> {
+    let queue = match &mut self.queue {
+      Some(queue) => queue,
+      None => return self.receiver.recv(),
+    };
+
+    loop {
+      if let Ok(message) = Inbox::next_queued(&self.receiver, queue) {
+        return Ok(message);
+      }
+      match self.receiver.recv()? {
+        ThreadMessage::Task(task) => queue.push(task),
+        wake => return Ok(wake),
+      }
+    }
+  }
+
+  fn try_recv(&mut self) -> Result<ThreadMessage, TryRecvError> {
+    match &mut self.queue {
+      Some(queue) => Inbox::next_queued(&self.receiver, queue),
+      None => self.receiver.try_recv(),
+    }
+  }
 }
 
 impl ThreadSender {
   // Sends task to paired thread.
   pub fn send_task(&self, task: Task) {
-    self.sender.send(task).unwrap();
+    self.pending.fetch_add(1, Ordering::SeqCst);
+    self.sender.send(ThreadMessage::Task(task)).unwrap();
   }
 }
 
 impl Thread {
   pub fn new(uuid: usize, config: ThreadConfig) -> Self {
     let (sender, receiver) = channel();
+    let queue = match config.driver {
+      ThreadDriver::Priority => Some(PriorityQueue::new(STARVATION_LIMIT)),
+      _ => None,
+    };
 
     Thread { 
       uuid: uuid,
+      executor: Executor::new(sender.clone()),
       sender: sender,
-      receiver: Some(receiver),
+      receiver: Some(Inbox {
+        receiver: receiver,
+        queue: queue,
+      }),
+      pending: Arc::new(AtomicUsize::new(0)),
       config: config,
     }
   }
 
   pub fn create_sender(&self) -> ThreadSender {
     ThreadSender {
+      uuid: self.uuid,
+      name: self.config.name.clone(),
       sender: self.sender.clone(),
+      pending: self.pending.clone(),
     }
   }
 
@@ -57,16 +138,18 @@ impl Thread {
       ThreadType::WebWorker => {},
       ThreadType::Thread => {
         let thread_name = self.config.name.clone();
-        let receiver = self.receiver.take().unwrap();
+        let mut receiver = self.receiver.take().unwrap();
         let thread_context = context.clone();
         let uuid = self.uuid;
+        let pending = self.pending.clone();
+        let mut executor = Executor::new(self.sender.clone());
 
         builder.spawn(move || {
           loop {
             let result = receiver.recv();
             match result {
-              Ok(received_task) => {
-                thread_context.handle_schedule(uuid, &received_task);
+              Ok(message) => {
+                handle_message(&thread_context, uuid, &thread_name, &pending, &mut executor, message);
               },
               Err(err) => {
                 println!("[{n}] Error while receiving task {:?}", err, n = thread_name);
@@ -78,6 +161,50 @@ impl Thread {
     }
   }
 
+  /// Handles all pending tasks and wakes of a thread that has not been spawned without
+  /// blocking. Returns the number of handled tasks, async ones may still be in flight.
+  pub fn poll(&mut self, context: &Context) -> usize {
+    let receiver = match &mut self.receiver {
+      Some(receiver) => receiver,
+      None => return 0,
+    };
+
+    let mut handled = 0;
+    loop {
+      match receiver.try_recv() {
+        Ok(message) => {
+          if handle_message(context, self.uuid, &self.config.name, &self.pending, &mut self.executor, message) {
+            handled += 1;
+          }
+        },
+        Err(TryRecvError::Empty) | Err(TryRecvError::Disconnected) => return handled,
+      }
+    }
+  }
+
+  /// Thread configuration.
+  pub fn get_config(&self) -> &ThreadConfig {
+    &self.config
+  }
+
+  /// Thread uuid.
+  pub fn get_uuid(&self) -> usize {
+    self.uuid
+  }
+
+  /// Handles the tasks of the thread for as long as its channel is open, blocking the calling
+  /// thread. Used by web workers, which can't be spawned from Rust.
+  pub fn run(&mut self, context: &Context) {
+    let receiver = match &mut self.receiver {
+      Some(receiver) => receiver,
+      None => return,
+    };
+
+    while let Ok(message) = receiver.recv() {
+      handle_message(context, self.uuid, &self.config.name, &self.pending, &mut self.executor, message);
+    }
+  }
+
   /// Blocks optionally the main thread to handle task.
   pub fn block(&mut self, context: &Context) {
     if let ThreadType::Main = self.config.thread_type {
@@ -85,12 +212,12 @@ impl Thread {
       return;
     }
 
-    let receiver = self.receiver.take().unwrap();
+    let mut receiver = self.receiver.take().unwrap();
     loop {
       let result =  receiver.recv();
       match result {
-        Ok(received_task) => {
-          context.handle_schedule(self.uuid, &received_task);
+        Ok(message) => {
+          handle_message(context, self.uuid, &self.config.name, &self.pending, &mut self.executor, message);
         },
         Err(err) => {
           println!("Error while receiving task {:?}", err);
@@ -98,4 +225,44 @@ impl Thread {
       }
     }
   }
-}
\ No newline at end of file
+}
+
+/// Handles a message received by a thread, the futures of async handlers are spawned on
+/// the executor of the thread. Returns whether the message was a task.
+fn handle_message(
+  context: &Context,
+  uuid: usize,
+  name: &str,
+  pending: &Arc<AtomicUsize>,
+  executor: &mut Executor,
+  message: ThreadMessage,
+) -> bool {
+  let task = match message {
+    ThreadMessage::Task(task) => task,
+    ThreadMessage::Wake(id) => {
+      executor.wake(id);
+      return false;
+    },
+  };
+
+  match context.handle_schedule(uuid, &task) {
+    Ok(Some(future)) => {
+      let name = name.to_string();
+      let pending = pending.clone();
+      executor.spawn(future.map(move |result| {
+        if let Err(err) = result {
+          println!("[{n}] Error while handling task: {}", err, n = name);
+        }
+        pending.fetch_sub(1, Ordering::SeqCst);
+      }).boxed());
+    },
+    Ok(None) => {
+      pending.fetch_sub(1, Ordering::SeqCst);
+    },
+    Err(err) => {
+      println!("[{n}] Error while handling task: {}", err, n = name);
+      pending.fetch_sub(1, Ordering::SeqCst);
+    },
+  }
+  true
+}
@@ -0,0 +1,68 @@
+use std::collections::VecDeque;
+
+use crate::config::TaskPriority;
+use crate::task::Task;
+
+/// Number of tasks that may be handled before a waiting task, until it is treated
+/// as one priority more urgent.
+pub const STARVATION_LIMIT: u64 = 16;
+
+struct Queued {
+  /// Number of handled tasks when the task was queued.
+  handled: u64,
+  task: Task,
+}
+
+/// Tasks of a thread with the `priority` driver. The most urgent task is handled first,
+/// tasks of the same priority in the order they were received. Waiting tasks age, so a
+/// steady stream of urgent tasks can't starve the less urgent ones.
+pub struct PriorityQueue {
+  /// One queue per priority, most urgent first.
+  queues: Vec<VecDeque<Queued>>,
+  handled: u64,
+  starvation_limit: u64,
+}
+
+impl PriorityQueue {
+  pub fn new(starvation_limit: u64) -> Self {
+    PriorityQueue {
+      queues: TaskPriority::ALL.iter().map(|_| VecDeque::new()).collect(),
+      handled: 0,
+      starvation_limit: starvation_limit.max(1),
+    }
+  }
+
+  pub fn push(&mut self, task: Task) {
+    let priority = task.priority.unwrap_or_default();
+    self.queues[priority as usize].push_back(Queued {
+      handled: self.handled,
+      task: task,
+    });
+  }
+
+  /// Takes the task with the most urgent aged priority, ties are won by the task
+  /// that waits longer.
+  pub fn pop(&mut self) -> Option<Task> {
+    let handled = self.handled;
+    let starvation_limit = self.starvation_limit;
+    let index = self.queues.iter()
+      .enumerate()
+      .filter_map(|(level, queue)| queue.front().map(|queued| {
+        let aged = (handled - queued.handled) / starvation_limit;
+        ((level as u64).saturating_sub(aged), queued.handled, level)
+      }))
+      .min()
+      .map(|(_, _, level)| level)?;
+
+    self.handled += 1;
+    self.queues[index].pop_front().map(|queued| queued.task)
+  }
+
+  pub fn len(&self) -> usize {
+    self.queues.iter().map(|queue| queue.len()).sum()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+}
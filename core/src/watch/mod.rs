@@ -0,0 +1,176 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::config::{layering, Configuration, RepoType};
+
+/// Folders that are written by builds, git or the generator itself and never watched.
+const IGNORED_FOLDERS: &[&str] = &["target", ".omnidux", ".git"];
+
+/// Time between two scans of the watched files.
+pub const POLL_INTERVAL: Duration = Duration::from_millis(200);
+/// Time without file events before a burst of events is reported as one change.
+pub const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Files and folders a project is generated from: the configuration file, the
+/// files it extends and the sources of all local repos.
+pub fn watched_paths(config_file: &Path, config: Option<&Configuration>) -> Vec<PathBuf> {
+  let mut paths = match layering::load_layers(config_file) {
+    Ok(layers) => layers.into_iter().map(|layer| layer.file).collect(),
+    Err(_) => vec![config_file.to_path_buf()],
+  };
+
+  let project = config_file.parent().unwrap_or_else(|| Path::new(""));
+  if let Some(config) = config {
+    for repo in config.repos.iter().filter(|repo| repo.repo_type == RepoType::Local) {
+      paths.push(project.join(repo.path()));
+    }
+  }
+
+  paths
+}
+
+/// Modification time and size of every file below the watched paths.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Snapshot {
+  files: BTreeMap<PathBuf, (Option<SystemTime>, u64)>,
+}
+
+impl Snapshot {
+  /// Scans the watched paths, missing paths are skipped.
+  pub fn scan(paths: &[PathBuf]) -> Self {
+    let mut snapshot = Snapshot::default();
+    for path in paths {
+      snapshot.scan_path(path);
+    }
+    snapshot
+  }
+
+  fn scan_path(&mut self, path: &Path) {
+    let metadata = match fs::metadata(path) {
+      Ok(metadata) => metadata,
+      Err(_) => return,
+    };
+
+    if metadata.is_file() {
+      self.files.insert(path.to_path_buf(), (metadata.modified().ok(), metadata.len()));
+      return;
+    }
+
+    let entries = match fs::read_dir(path) {
+      Ok(entries) => entries,
+      Err(_) => return,
+    };
+    for entry in entries.filter_map(|entry| entry.ok()) {
+      let ignored = entry.file_name().to_str().map(|name| IGNORED_FOLDERS.contains(&name)).unwrap_or(false);
+      if !ignored {
+        self.scan_path(&entry.path());
+      }
+    }
+  }
+
+  /// Files that were created, modified or removed since the older snapshot.
+  pub fn changes(&self, older: &Snapshot) -> Vec<PathBuf> {
+    let mut changes: BTreeSet<&PathBuf> = BTreeSet::new();
+    for (file, state) in &self.files {
+      if older.files.get(file) != Some(state) {
+        changes.insert(file);
+      }
+    }
+    for file in older.files.keys() {
+      if !self.files.contains_key(file) {
+        changes.insert(file);
+      }
+    }
+
+    changes.into_iter().cloned().collect()
+  }
+}
+
+/// Collects file events until no new ones arrived for the quiet period, so saving
+/// several files at once only triggers a single regeneration.
+#[derive(Debug, Clone)]
+pub struct Debouncer {
+  quiet: Duration,
+  pending: BTreeSet<PathBuf>,
+  last_event: Option<Instant>,
+}
+
+impl Debouncer {
+  pub fn new(quiet: Duration) -> Self {
+    Debouncer {
+      quiet: quiet,
+      pending: BTreeSet::new(),
+      last_event: None,
+    }
+  }
+
+  /// Records the changed files, an empty list is no event.
+  pub fn push(&mut self, files: Vec<PathBuf>, now: Instant) {
+    if files.is_empty() {
+      return;
+    }
+
+    self.pending.extend(files);
+    self.last_event = Some(now);
+  }
+
+  /// Takes the pending files once the quiet period after the last event passed.
+  pub fn ready(&mut self, now: Instant) -> Option<Vec<PathBuf>> {
+    match self.last_event {
+      Some(last_event) if now.duration_since(last_event) >= self.quiet => {
+        self.last_event = None;
+        Some(std::mem::replace(&mut self.pending, BTreeSet::new()).into_iter().collect())
+      },
+      _ => None,
+    }
+  }
+}
+
+/// Watches files by polling their modification times.
+pub struct Watcher {
+  paths: Vec<PathBuf>,
+  snapshot: Snapshot,
+  debouncer: Debouncer,
+  interval: Duration,
+}
+
+impl Watcher {
+  pub fn new(paths: Vec<PathBuf>, interval: Duration, quiet: Duration) -> Self {
+    Watcher {
+      snapshot: Snapshot::scan(&paths),
+      paths: paths,
+      debouncer: Debouncer::new(quiet),
+      interval: interval,
+    }
+  }
+
+  /// Replaces the watched paths, e.g. after repos were added to the configuration.
+  /// Files that only start to be watched are not reported as changed.
+  pub fn set_paths(&mut self, paths: Vec<PathBuf>) {
+    if paths != self.paths {
+      self.snapshot = Snapshot::scan(&paths);
+      self.paths = paths;
+    }
+  }
+
+  /// Scans the watched files once, returns the changed files once a burst of changes settled.
+  pub fn poll(&mut self, now: Instant) -> Option<Vec<PathBuf>> {
+    let snapshot = Snapshot::scan(&self.paths);
+    self.debouncer.push(snapshot.changes(&self.snapshot), now);
+    self.snapshot = snapshot;
+    self.debouncer.ready(now)
+  }
+
+  /// Blocks until files changed.
+  pub fn wait(&mut self) -> Vec<PathBuf> {
+    loop {
+      thread::sleep(self.interval);
+      if let Some(changes) = self.poll(Instant::now()) {
+        return changes;
+      }
+    }
+  }
+}
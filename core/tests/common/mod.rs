@@ -0,0 +1,13 @@
+//! Helpers shared by the integration tests.
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+/// Creates an empty folder inside of the temp directory.
+pub fn temp_folder(name: &str) -> PathBuf {
+  let folder = env::temp_dir().join(format!("omnidux-{}-{}", name, std::process::id()));
+  let _ = fs::remove_dir_all(&folder);
+  fs::create_dir_all(&folder).unwrap();
+  folder
+}
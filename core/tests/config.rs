@@ -0,0 +1,500 @@
+extern crate omnidux_core;
+extern crate serde_json;
+extern crate serde_yaml;
+
+use std::fs;
+
+use omnidux_core::config::{
+  build_config_from_file, build_config_from_str, migrate_config_str,
+  check_config_file, CheckReport, BuildError, Location, Platform, ThreadDriver, ScheduleTarget, TargetExpression, CURRENT_VERSION, LEGACY_VERSION,
+  ScheduleScaleValue, ScaleContext, ResolvedScale, RawConfiguration, TaskPriority,
+};
+use omnidux_core::config::layout::quote_bare_wildcards;
+use omnidux_core::config::schema::json_schema;
+use serde_json::Value;
+
+const VALID: &str = "
+name: test
+target:
+  platform: test
+  engine: native
+repos:
+  - type: local
+    source: ./repos/core
+scopes:
+  - repo: core
+threads:
+  - type: thread
+    name: worker1
+    driver: mpsc-fifo
+setup:
+  - repo: core
+    target: worker1
+";
+
+#[test]
+fn config_valid() {
+  assert!(build_config_from_str(VALID).is_ok());
+}
+
+#[test]
+fn config_validation_reports_located_diagnostics() {
+  let invalid = "
+name: test
+target:
+  platform: test
+  engine: native
+repos:
+  - type: local
+    source: ./repos/core
+scopes:
+  - repo: core
+    scopes:
+      - repo: board
+threads:
+  - type: thread
+    name: worker1
+    driver: mpsc-fifo
+  - type: thread
+    name: worker1
+    driver: mpsc-fifo
+setup:
+  - repo: page
+    target:
+      - worker1
+      - worker3
+    min: 2
+    max: 1
+";
+
+  let diagnostics = match build_config_from_str(invalid) {
+    Err(BuildError::ValidationError(diagnostics)) => diagnostics,
+    other => panic!("Expected validation error, got {:?}", other),
+  };
+
+  let paths: Vec<&str> = diagnostics.iter().map(|d| d.path.as_str()).collect();
+  assert_eq!(paths, vec![
+    "threads[1].name",
+    "scopes[0].scopes[0].repo",
+    "setup[0].repo",
+    "setup[0].target[1]",
+    "setup[0].min",
+  ]);
+
+  assert_eq!(diagnostics[0].location, Some(Location { line: 18, column: 11 }));
+  assert_eq!(diagnostics[1].location, Some(Location { line: 12, column: 15 }));
+  assert_eq!(diagnostics[3].location, Some(Location { line: 24, column: 9 }));
+}
+
+#[test]
+fn config_quoting_wildcards_keeps_line_endings() {
+  let content = "setup:\r\n  - repo: core\r\n    target: * # all threads\r\n    min: 0\n";
+  assert_eq!(
+    quote_bare_wildcards(content),
+    "setup:\r\n  - repo: core\r\n    target: \"*\" # all threads\r\n    min: 0\n",
+  );
+
+  let invalid = format!("{}\n  - repo: page\n    target: worker3\n", VALID.trim_end());
+  let diagnostics = match build_config_from_str(&invalid) {
+    Err(BuildError::ValidationError(diagnostics)) => diagnostics,
+    other => panic!("Expected validation error, got {:?}", other),
+  };
+  let target = diagnostics.iter().find(|d| d.path == "setup[1].target").unwrap();
+  assert_eq!(target.location, Some(Location { line: 19, column: 13 }));
+}
+
+#[test]
+fn config_loads_ios_example() {
+  let config = build_config_from_file("../examples/kanbanboard/omnidux.ios.yaml".to_string()).unwrap();
+
+  assert_eq!(config.version, LEGACY_VERSION);
+  assert_eq!(config.name, "omnidux-example-kanbanboard");
+  assert_eq!(config.target.platform, Platform::IOS);
+  assert_eq!(config.threads.len(), 4);
+  assert_eq!(config.setup.len(), 1);
+  assert_eq!(config.repos.len(), 3);
+}
+
+#[test]
+fn config_loads_web_example() {
+  let config = build_config_from_file("../examples/kanbanboard/omnidux.web.yaml".to_string()).unwrap();
+
+  assert_eq!(config.version, CURRENT_VERSION);
+  assert_eq!(config.name, "kanbanboard");
+  assert_eq!(config.target.platform, Platform::Web);
+  assert_eq!(config.threads[0].driver, ThreadDriver::WorkerDirect);
+  assert_eq!(config.setup.len(), 7);
+  assert_eq!(config.setup[0].target, ScheduleTarget::Expression("*".to_string()));
+
+  // Schedules without `max` may use all of their targets, so `core` can set `min: 2` alone.
+  assert_eq!(config.setup[5].repo, "core");
+  assert_eq!(config.setup[5].min, ScheduleScaleValue::Static(2));
+  assert_eq!(config.setup[5].max, ScheduleScaleValue::Dynamic("all".to_string()));
+}
+
+#[test]
+fn config_migrates_legacy_layout() {
+  let migrated = migrate_config_str(VALID).unwrap();
+  let config = build_config_from_str(&migrated).unwrap();
+  let legacy = build_config_from_str(VALID).unwrap();
+
+  assert_eq!(config.version, CURRENT_VERSION);
+  assert_eq!(config.threads, legacy.threads);
+  assert_eq!(config.setup, legacy.setup);
+}
+
+#[test]
+fn config_rejects_mixed_layouts() {
+  let mixed = format!("{}application:\n  threads: []\n  repos: []\n", VALID);
+
+  match build_config_from_str(&mixed) {
+    Err(BuildError::ValidationError(diagnostics)) => {
+      let paths: Vec<&str> = diagnostics.iter().map(|d| d.path.as_str()).collect();
+      assert_eq!(paths, vec!["threads", "setup"]);
+    },
+    other => panic!("Expected validation error, got {:?}", other),
+  }
+}
+
+#[test]
+fn config_merges_layers() {
+  let config = build_config_from_file("tests/fixtures/layering/omnidux.test.yaml".to_string()).unwrap();
+
+  assert_eq!(config.target.engine, "headless");
+  assert_eq!(config.target.platform, Platform::Test);
+  assert_eq!(config.threads.len(), 2);
+  assert_eq!(config.threads[0].driver, ThreadDriver::Direct);
+  assert_eq!(config.setup.len(), 1);
+  assert_eq!(config.setup[0].target, ScheduleTarget::Multiple(vec!["worker1".to_string(), "worker2".to_string()]));
+
+  let origin = config.origin_of("application.threads[0].type").unwrap();
+  assert!(origin.file.ends_with("base.yaml"));
+  assert_eq!(origin.path, "application.threads[0].type");
+
+  let origin = config.origin_of("application.threads[1].name").unwrap();
+  assert!(origin.file.ends_with("omnidux.test.yaml"));
+  assert_eq!(origin.path, "application.threads[1].name");
+}
+
+#[test]
+fn config_rejects_extends_cycles() {
+  let diagnostics = match build_config_from_file("tests/fixtures/layering/cycle/omnidux.test.yaml".to_string()) {
+    Err(BuildError::ValidationError(diagnostics)) => diagnostics,
+    other => panic!("Expected validation error, got {:?}", other),
+  };
+
+  assert_eq!(diagnostics[0].path, "extends");
+  assert!(diagnostics[0].message.ends_with("omnidux.test.yaml extends itself"));
+}
+
+#[test]
+fn config_extends_implicit_base() {
+  let config = build_config_from_file("../examples/kanbanboard/omnidux.web.yaml".to_string()).unwrap();
+
+  let origin = config.origin_of("scopes[0].scopes[1].repo").unwrap();
+  assert!(origin.file.ends_with("omnidux.yaml"));
+  assert!(config.origin_of("application.threads[0]").unwrap().file.ends_with("omnidux.web.yaml"));
+}
+
+const SELECTOR: &str = "
+name: test
+target:
+  platform: test
+  engine: native
+repos:
+  - type: local
+    source: ./repos/core
+scopes: []
+threads:
+  - type: thread
+    name: worker1
+    driver: mpsc-fifo
+    tags: [background]
+  - type: thread
+    name: worker2
+    driver: mpsc-fifo
+  - type: thread
+    name: shadow
+    driver: mpsc-fifo
+    tags: [background]
+  - type: main
+    name: main
+    driver: direct
+setup:
+  - repo: core
+    target: \"* !main\"
+";
+
+#[test]
+fn config_selects_targets() {
+  let config = build_config_from_str(SELECTOR).unwrap();
+  let select = |expression: &str| TargetExpression::parse(expression).unwrap().select(&config.threads);
+
+  assert_eq!(config.setup[0].target_threads, vec![0, 1, 2]);
+  assert_eq!(select("*"), vec![0, 1, 2, 3]);
+  assert_eq!(select("worker*"), vec![0, 1]);
+  assert_eq!(select("tag:background"), vec![0, 2]);
+  assert_eq!(select("type:main shadow"), vec![2, 3]);
+  assert_eq!(select("worker? !worker2"), vec![0]);
+  assert_eq!(select("!type:thread"), vec![3]);
+}
+
+#[test]
+fn config_rejects_invalid_targets() {
+  let invalid = SELECTOR.replace("\"* !main\"", "\"* !worker3 type:fiber\"");
+
+  match build_config_from_str(&invalid) {
+    Err(BuildError::ValidationError(diagnostics)) => {
+      assert_eq!(diagnostics.len(), 1);
+      assert!(diagnostics[0].message.contains("unknown thread type `fiber`"));
+    },
+    other => panic!("Expected validation error, got {:?}", other),
+  }
+
+  let unknown = SELECTOR.replace("\"* !main\"", "\"* !worker3\"");
+  match build_config_from_str(&unknown) {
+    Err(BuildError::ValidationError(diagnostics)) => {
+      assert_eq!(diagnostics[0].message, "schedule targets unknown thread `worker3`");
+    },
+    other => panic!("Expected validation error, got {:?}", other),
+  }
+}
+
+#[test]
+fn config_scale_defaults() {
+  let config = build_config_from_str(VALID).unwrap();
+
+  assert_eq!(config.setup[0].min, ScheduleScaleValue::Static(1));
+  assert_eq!(config.setup[0].max, ScheduleScaleValue::Dynamic("all".to_string()));
+  assert_eq!(config.setup[0].resolve_scale(8), ResolvedScale { min: 1, max: 1 });
+}
+
+#[test]
+fn config_resolves_machine_dependent_scales_at_boot() {
+  // Whether `cpu` exceeds `max` depends on the machine, so loading never rejects it.
+  let config = build_config_from_str(&SELECTOR.replace("\"* !main\"", "\"* !main\"\n    min: cpu\n    max: 2")).unwrap();
+
+  assert_eq!(config.setup[0].resolve_scale(1), ResolvedScale { min: 1, max: 2 });
+  assert_eq!(config.setup[0].resolve_scale(16), ResolvedScale { min: 2, max: 2 });
+
+  // A schedule with targets keeps at least one thread.
+  let config = build_config_from_str(&SELECTOR.replace("\"* !main\"", "\"* !main\"\n    max: cpu-8")).unwrap();
+  assert_eq!(config.setup[0].resolve_scale(4), ResolvedScale { min: 1, max: 1 });
+}
+
+#[test]
+fn config_resolves_dynamic_scales() {
+  let context = ScaleContext { matching_threads: 4, available_parallelism: 3 };
+  let resolve = |value: &str| ScheduleScaleValue::Dynamic(value.to_string()).resolve(&context);
+
+  assert_eq!(resolve("all"), Ok(4));
+  assert_eq!(resolve("cpu"), Ok(3));
+  assert_eq!(resolve("cpu-1"), Ok(2));
+  assert_eq!(resolve("cpu+4"), Ok(4));
+  assert_eq!(resolve("cpu-8"), Ok(0));
+  assert_eq!(resolve("50%"), Ok(2));
+  assert_eq!(resolve("30%"), Ok(2));
+  assert_eq!(resolve("3"), Ok(3));
+  assert!(resolve("half").is_err());
+  assert!(resolve("120%").is_err());
+  assert_eq!(ScheduleScaleValue::Static(4).resolve(&context), Ok(4));
+  assert_eq!(
+    ScheduleScaleValue::Static(9).resolve(&context),
+    Err("scale (9) exceeds the 4 threads selected by the target".to_string()),
+  );
+}
+
+#[test]
+fn config_reads_task_priorities() {
+  let config = build_config_from_str(&SELECTOR
+    .replace("driver: direct", "driver: priority")
+    .replace("\"* !main\"", "\"* !main\"\n    priority: user-blocking")).unwrap();
+
+  assert_eq!(config.threads[3].driver, ThreadDriver::Priority);
+  assert_eq!(config.setup[0].priority, TaskPriority::UserBlocking);
+  assert_eq!(build_config_from_str(SELECTOR).unwrap().setup[0].priority, TaskPriority::Normal);
+
+  let unknown = SELECTOR.replace("\"* !main\"", "\"* !main\"\n    priority: urgent");
+  assert!(build_config_from_str(&unknown).is_err());
+}
+
+#[test]
+fn config_rejects_invalid_scales() {
+  let invalid = SELECTOR.replace("\"* !main\"", "\"* !main\"\n    min: all\n    max: 50%");
+
+  match build_config_from_str(&invalid) {
+    Err(BuildError::ValidationError(diagnostics)) => {
+      assert_eq!(diagnostics[0].path, "setup[0].min");
+      assert_eq!(diagnostics[0].message, "min (3) must not be greater than max (2)");
+    },
+    other => panic!("Expected validation error, got {:?}", other),
+  }
+
+  let exceeding = SELECTOR.replace("\"* !main\"", "\"* !main\"\n    max: 5");
+  match build_config_from_str(&exceeding) {
+    Err(BuildError::ValidationError(diagnostics)) => {
+      assert_eq!(diagnostics.len(), 1);
+      assert_eq!(diagnostics[0].path, "setup[0].max");
+      assert_eq!(diagnostics[0].message, "scale (5) exceeds the 3 threads selected by the target");
+      assert!(diagnostics[0].location.is_some());
+    },
+    other => panic!("Expected validation error, got {:?}", other),
+  }
+
+  let unknown = SELECTOR.replace("\"* !main\"", "\"* !main\"\n    max: some");
+  match build_config_from_str(&unknown) {
+    Err(BuildError::ValidationError(diagnostics)) => assert_eq!(diagnostics[0].path, "setup[0].max"),
+    other => panic!("Expected validation error, got {:?}", other),
+  }
+}
+
+/// Resolves `$ref`s and `allOf` wrappers of a schema against its definitions.
+fn resolve_schema<'a>(schema: &'a Value, root: &'a Value) -> &'a Value {
+  if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+    let name = reference.trim_start_matches("#/definitions/");
+    return resolve_schema(&root["definitions"][name], root);
+  }
+  if let Some(all_of) = schema.get("allOf").and_then(Value::as_array) {
+    return resolve_schema(&all_of[0], root);
+  }
+  schema
+}
+
+/// Checks that every key and enum value of the value is described by the schema,
+/// trying each variant of `anyOf` and `oneOf` schemas.
+fn describe(value: &Value, schema: &Value, root: &Value, path: &str) -> Result<(), String> {
+  let schema = resolve_schema(schema, root);
+  let variants: Vec<&Value> = ["anyOf", "oneOf"].iter()
+    .filter_map(|key| schema.get(*key).and_then(Value::as_array))
+    .flat_map(|variants| variants.iter())
+    .collect();
+
+  if !variants.is_empty() {
+    let mut errors = Vec::new();
+    for variant in variants {
+      let mut merged = resolve_schema(variant, root).clone();
+      if let (Some(own), Some(properties)) = (schema.get("properties"), merged.get_mut("properties")) {
+        for (key, property) in own.as_object().unwrap() {
+          properties[key] = property.clone();
+        }
+      }
+      match describe(value, &merged, root, path) {
+        Ok(()) => return Ok(()),
+        Err(error) => errors.push(error),
+      }
+    }
+    return Err(errors.join(", "));
+  }
+
+  match value {
+    Value::Object(object) => {
+      for key in schema.get("required").and_then(Value::as_array).into_iter().flatten().filter_map(Value::as_str) {
+        if !object.contains_key(key) {
+          return Err(format!("`{}.{}` is required", path, key));
+        }
+      }
+      for (key, child) in object {
+        let property = schema["properties"].get(key).ok_or(format!("`{}.{}` is not described", path, key))?;
+        describe(child, property, root, &format!("{}.{}", path, key))?;
+      }
+    },
+    Value::Array(items) => {
+      for item in items {
+        describe(item, &schema["items"], root, &format!("{}[]", path))?;
+      }
+    },
+    Value::String(string) => {
+      if let Some(values) = schema.get("enum").and_then(Value::as_array) {
+        if !values.contains(&Value::String(string.clone())) {
+          return Err(format!("`{}` does not allow `{}`", path, string));
+        }
+      }
+    },
+    _ => {},
+  }
+
+  Ok(())
+}
+
+#[test]
+fn config_schema_matches_serde_definitions() {
+  let schema = serde_json::to_value(json_schema()).unwrap();
+
+  for config in &[
+    build_config_from_file("../examples/kanbanboard/omnidux.web.yaml".to_string()).unwrap(),
+    build_config_from_str(SELECTOR).unwrap(),
+    build_config_from_str(&VALID.replace("type: local", "type: git\n    url: file:///repos/core.git\n    tag: v1")).unwrap(),
+    build_config_from_str(&VALID.replace("target: worker1", "target: worker1\n    priority: idle")).unwrap(),
+  ] {
+    let raw = serde_json::to_value(RawConfiguration::from(config.clone())).unwrap();
+    describe(&raw, &schema, &schema, "").unwrap();
+  }
+
+  // Every checked-in file is valid on its own, editors check them one by one.
+  for file in &["omnidux.yaml", "omnidux.ios.yaml", "omnidux.web.yaml"] {
+    let content = fs::read_to_string(format!("../examples/kanbanboard/{}", file)).unwrap();
+    let value: Value = serde_yaml::from_str(&quote_bare_wildcards(&content)).unwrap();
+    describe(&value, &schema, &schema, "").map_err(|error| format!("{}: {}", file, error)).unwrap();
+  }
+  assert_eq!(
+    describe(&serde_json::json!({ "threads": [{ "name": "main" }] }), &schema, &schema, ""),
+    Err("`.threads[].driver` is required".to_string()),
+  );
+
+  let drivers = &schema["definitions"]["ThreadDriver"];
+  let names: Vec<&str> = drivers["oneOf"].as_array().map(|variants| {
+    variants.iter().flat_map(|variant| variant["enum"].as_array().unwrap()).filter_map(Value::as_str).collect()
+  }).unwrap_or_else(|| drivers["enum"].as_array().unwrap().iter().filter_map(Value::as_str).collect());
+  assert_eq!(names, vec!["mpsc-fifo", "direct", "worker-direct", "default", "priority"]);
+  assert!(schema["properties"]["extends"].is_object());
+}
+
+#[test]
+fn config_validates_repo_manifests() {
+  let diagnostics = match build_config_from_file("tests/fixtures/manifests/omnidux.test.yaml".to_string()) {
+    Err(BuildError::ValidationError(diagnostics)) => diagnostics,
+    other => panic!("Expected validation error, got {:?}", other),
+  };
+
+  let messages: Vec<String> = diagnostics.iter().map(|d| d.to_string()).collect();
+  assert_eq!(messages.len(), 3, "{:#?}", messages);
+  assert!(messages[0].ends_with("board/omnidux.repo.yaml:12:1: scopes: repo `board` may only be nested in the scope of page"));
+  assert!(messages[1].ends_with("page/omnidux.repo.yaml:5:11: tasks[1].name: task `RenderPage` is declared more than once"));
+  assert!(messages[2].ends_with("page/omnidux.repo.yaml:11:14: requires[1].capsule: repo `board` does not export capsule `ColumnCapsule`"));
+}
+
+#[test]
+fn config_check_reports_missing_sources() {
+  let file = "tests/fixtures/check/omnidux.test.yaml".to_string();
+  build_config_from_file(file.clone()).unwrap();
+
+  let result = check_config_file(file.clone());
+  let error = result.as_ref().err().unwrap();
+  assert_eq!(error.exit_code(), 4);
+
+  let report = CheckReport::new(file.clone(), &result);
+  assert_eq!(report.status, "validation");
+  assert_eq!(report.diagnostics.len(), 1);
+  assert_eq!(
+    report.diagnostics[0].to_string(),
+    format!("{}:10:13: repos[1].source: source folder `./repos/missing` does not exist", file),
+  );
+
+  let json: Value = serde_json::from_str(&report.to_json()).unwrap();
+  assert_eq!(json["status"], "validation");
+  assert_eq!(json["diagnostics"][0]["path"], "repos[1].source");
+  assert_eq!(json["diagnostics"][0]["location"]["line"], 10);
+}
+
+#[test]
+fn config_check_distinguishes_error_kinds() {
+  let missing = check_config_file("tests/fixtures/check/omnidux.missing.yaml".to_string()).err().unwrap();
+  assert_eq!((missing.kind(), missing.exit_code()), ("io", 3));
+
+  let parse = build_config_from_str("target: [").err().unwrap();
+  assert_eq!((parse.kind(), parse.exit_code()), ("parse", 2));
+  assert_eq!(parse.diagnostics().len(), 1);
+
+  let report = CheckReport::new("valid.yaml".to_string(), &build_config_from_str(VALID));
+  assert_eq!((report.status, report.diagnostics.len()), ("ok", 0));
+}
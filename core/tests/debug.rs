@@ -0,0 +1,177 @@
+#[macro_use]
+extern crate omnidux_core;
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+use omnidux_core::config::{build_config_from_str, Configuration};
+use omnidux_core::debug::{Debugger, Execution};
+
+#[allow(dead_code, non_snake_case)]
+mod board {
+  use omnidux_core::scheduler::strategy::{ScheduleStrategy, take_first};
+  use omnidux_core::task::{TaskHandler, Task};
+  use omnidux_core::capsule::CapsuleContent;
+
+  impl_default_capsule! (ColumnCapsule, usize, usize, inspectable);
+
+  /// Keys of this capsule have no text form, so the debugger can't inspect it.
+  #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+  pub struct CardId (usize);
+  impl_default_capsule! (CardCapsule, CardId, usize);
+
+  #[derive(Clone)]
+  pub struct Refresh { uuid: usize }
+  impl TaskHandler for Refresh {
+    type Input = ();
+    type Output = ();
+
+    fn handle(&self, _task: &Task, _input: &()) {}
+  }
+  impl_strategy! (Refresh, take_first);
+
+  #[derive(Clone)]
+  pub struct Rename { uuid: usize }
+  impl TaskHandler for Rename {
+    type Input = String;
+    type Output = ();
+
+    fn handle(&self, _task: &Task, input: &String) {
+      assert_eq!(input, "Sprint 12");
+    }
+  }
+  impl_strategy! (Rename, take_first);
+
+  create_repo! {
+    tasks: [
+      Refresh,
+      Rename,
+    ],
+    capsules: [
+      ColumnCapsule,
+      CardCapsule,
+    ]
+  }
+}
+
+/// Only has a main thread, so tasks are handled whenever the debugger polls.
+fn config(setup: &str) -> Configuration {
+  build_config_from_str(&format!("
+name: debug
+target:
+  platform: test
+  engine: native
+repos:
+  - type: local
+    source: ./board
+  - type: local
+    source: ./page
+threads:
+  - type: main
+    name: main
+    driver: direct
+setup:
+{}", setup)).unwrap()
+}
+
+fn debugger() -> Debugger {
+  let mut debugger = Debugger::new(&config("  - repo: board\n    target: main\n"))
+    .register(vec![debug_repo!("board", board)]);
+  assert!(debugger.boot().is_empty());
+  debugger
+}
+
+#[test]
+fn debug_boots_linked_repos() {
+  let config = config("  - repo: board\n    target: main\n  - repo: page\n    target: main\n");
+
+  let mut debugger = Debugger::new(&config).register(vec![debug_repo!("board", board)]);
+  assert_eq!(debugger.boot(), vec!["Repo `page` is not linked into the debugger, skipping it."]);
+  assert_eq!(
+    debugger.execute("repos"),
+    Ok("board\n  task    0  Refresh\n  task    1  Rename\n  capsule    ColumnCapsule\n  capsule    CardCapsule".to_string()),
+  );
+  assert_eq!(debugger.execute("threads"), Ok("   0  main  Main  Direct".to_string()));
+
+  // Without any linked repo there is nothing to debug.
+  assert!(Debugger::new(&config).run().is_err());
+}
+
+#[test]
+fn debug_schedules_tasks() {
+  let mut debugger = debugger();
+
+  assert_eq!(debugger.execute("schedule board Refresh"), Ok("Scheduled board::Refresh on threads [0].".to_string()));
+  assert_eq!(debugger.execute("schedule board Rename Sprint 12 @main"), Ok("Scheduled board::Rename on threads [0].".to_string()));
+  assert_eq!(debugger.execute("schedule board Archive"), Err("Repo `board` has no task `Archive`.".to_string()));
+  assert_eq!(debugger.execute("schedule board Refresh @worker"), Err("Unknown thread `worker`.".to_string()));
+  assert_eq!(debugger.execute("schedule page Refresh"), Err("Unknown repo `page`.".to_string()));
+}
+
+#[test]
+fn debug_schedules_tasks_on_one_eligible_thread() {
+  let config = build_config_from_str("
+name: debug
+target:
+  platform: test
+  engine: native
+repos:
+  - type: local
+    source: ./board
+threads:
+  - type: main
+    name: main
+    driver: direct
+  - type: thread
+    name: worker1
+    driver: mpsc-fifo
+  - type: thread
+    name: worker2
+    driver: mpsc-fifo
+setup:
+  - repo: board
+    target: [worker1, worker2]
+").unwrap();
+  let mut debugger = Debugger::new(&config).register(vec![debug_repo!("board", board)]);
+  assert!(debugger.boot().is_empty());
+
+  assert_eq!(debugger.execute("schedule board Refresh"), Ok("Scheduled board::Refresh on threads [1].".to_string()));
+  assert_eq!(debugger.execute("schedule board Refresh @worker2"), Ok("Scheduled board::Refresh on threads [2].".to_string()));
+
+  let deadline = Instant::now() + Duration::from_secs(5);
+  let mut executions = Vec::new();
+  while executions.len() < 2 && Instant::now() < deadline {
+    executions.extend(debugger.take_executions());
+    thread::sleep(Duration::from_millis(1));
+  }
+  executions.sort_by_key(|execution| execution.thread);
+  assert_eq!(executions, vec![Execution { thread: 1, task: 0 }, Execution { thread: 2, task: 0 }]);
+}
+
+#[test]
+fn debug_reads_and_writes_capsules() {
+  let mut debugger = debugger();
+
+  assert_eq!(debugger.execute("get board ColumnCapsule 1"), Ok("Empty".to_string()));
+  assert_eq!(debugger.execute("set board ColumnCapsule 1 5"), Ok("Set board.ColumnCapsule[1].".to_string()));
+  assert_eq!(debugger.execute("get board ColumnCapsule 1"), Ok("Some(5)".to_string()));
+  assert_eq!(debugger.execute("get board ColumnCapsule first"), Err("Invalid key `first`.".to_string()));
+  assert_eq!(debugger.execute("set board ColumnCapsule 1 five"), Err("Invalid value `five`.".to_string()));
+  assert_eq!(debugger.execute("get board CardCapsule 1"), Err("Capsule does not support debugging.".to_string()));
+  assert_eq!(debugger.execute("get board RowCapsule 1"), Err("Repo `board` has no capsule `RowCapsule`.".to_string()));
+}
+
+#[test]
+fn debug_logs_executions() {
+  let mut debugger = debugger();
+
+  assert_eq!(debugger.execute("log"), Ok("No tasks ran since the last check.".to_string()));
+  debugger.execute("schedule board Refresh").unwrap();
+  debugger.execute("schedule board Rename Sprint 12").unwrap();
+  assert_eq!(debugger.execute("log"), Ok("[main] board::Refresh\n[main] board::Rename".to_string()));
+  assert_eq!(debugger.execute("log"), Ok("No tasks ran since the last check.".to_string()));
+
+  debugger.execute("schedule board Refresh").unwrap();
+  assert_eq!(debugger.take_executions(), vec![Execution { thread: 0, task: 0 }]);
+  assert!(debugger.take_executions().is_empty());
+}
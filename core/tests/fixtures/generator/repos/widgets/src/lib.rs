@@ -0,0 +1,6 @@
+use omnidux_core::capsule_get;
+use omnidux_core::scheduler::Context;
+
+pub fn is_visible(context: &Context, board: usize) -> bool {
+  capsule_get!(context, app_core, AppCapsule, board).is_some()
+}
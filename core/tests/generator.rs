@@ -0,0 +1,227 @@
+extern crate omnidux_core;
+extern crate insta;
+
+mod common;
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use insta::assert_snapshot;
+
+use omnidux_core::config::{build_config_from_file, build_config_from_str, Configuration, Platform};
+use omnidux_core::generator::diff::unified_diff;
+use omnidux_core::generator::files::{merge_user_regions, ChangeKind};
+use omnidux_core::generator::platform::{IOSGenerator, PlatformGenerator, TestGenerator, WebGenerator};
+use omnidux_core::generator::rust::RustProjectBuilder;
+use omnidux_core::source::resolve_repos;
+
+use common::temp_folder;
+
+const PROJECT: &str = "tests/fixtures/generator";
+
+fn builder() -> RustProjectBuilder {
+  let config = build_config_from_file(format!("{}/omnidux.test.yaml", PROJECT)).unwrap();
+  let repos = resolve_repos(&config, Path::new(PROJECT), false).unwrap();
+
+  RustProjectBuilder::new(config.name.clone())
+    .set_destination(format!("{}/.omnidux", PROJECT))
+    .set_repos(repos)
+    .set_config(config)
+    .set_omnidux_path(Path::new(PROJECT).join("../../../.."))
+}
+
+/// Content of the main.rs generated by the test backend.
+fn render_main(builder: RustProjectBuilder) -> String {
+  builder.render_entry().unwrap()
+    .into_iter()
+    .find(|file| file.path == Path::new("src/main.rs"))
+    .unwrap()
+    .content
+}
+
+#[test]
+fn generator_renders_main() {
+  let main = render_main(builder());
+  assert_snapshot!(main);
+}
+
+#[test]
+fn generator_creates_repo_per_setup_entry() {
+  let main = render_main(builder());
+
+  // Crates are named by manifest, Cargo.toml or the sys prefix and created in setup order.
+  let renderer = main.find("omnidux_sys_shadow_renderer::Repository::new(config.setup[0]").unwrap();
+  let widgets = main.find("kanban_widgets::Repository::new(config.setup[1]").unwrap();
+  let app = main.find("app_core::Repository::new(config.setup[2]").unwrap();
+  assert!(renderer < widgets && widgets < app);
+}
+
+fn empty_config() -> Configuration {
+  build_config_from_str("
+name: empty
+target:
+  platform: test
+  engine: native
+threads:
+  - type: main
+    name: main
+    driver: direct
+setup: []
+").unwrap()
+}
+
+#[test]
+fn generator_renders_main_without_repos() {
+  let config = empty_config();
+
+  let main = render_main(RustProjectBuilder::new(config.name.clone()).set_config(config));
+  assert_snapshot!(main);
+}
+
+#[test]
+fn generator_renders_cargo_toml() {
+  let cargo_toml = builder().render_cargo_toml().unwrap();
+  assert_snapshot!(cargo_toml);
+}
+
+#[test]
+fn generator_renders_workspace() {
+  let workspace = builder().render_workspace().unwrap();
+  assert_snapshot!(workspace);
+}
+
+#[test]
+fn generator_keeps_user_regions() {
+  let existing = "header\n// omnidux:begin-user main\nkeep();\n// omnidux:end-user main\nold\n";
+  let generated = "new header\n// omnidux:begin-user main\n// omnidux:end-user main\n// omnidux:begin-user other\n// omnidux:end-user other\n";
+
+  assert_eq!(
+    merge_user_regions(existing, generated),
+    "new header\n// omnidux:begin-user main\nkeep();\n// omnidux:end-user main\n// omnidux:begin-user other\n// omnidux:end-user other\n",
+  );
+}
+
+#[test]
+fn generator_only_writes_changed_files() {
+  let project = temp_folder("generator-incremental");
+  let builder = || {
+    RustProjectBuilder::new("empty".to_string())
+      .set_destination(project.join(".omnidux").to_string_lossy().into_owned())
+      .set_config(empty_config())
+  };
+
+  let changes = builder().build().unwrap();
+  assert!(changes.iter().all(|change| change.kind() == ChangeKind::Create));
+
+  // Files of the developer survive another generation.
+  let main = project.join(".omnidux/empty-main-lib/src/main.rs");
+  let content = fs::read_to_string(&main).unwrap()
+    .replace("  // omnidux:end-user main", "  println!(\"started\");\n  // omnidux:end-user main");
+  fs::write(&main, &content).unwrap();
+  fs::write(project.join(".omnidux/notes.txt"), "keep me").unwrap();
+
+  let changes = builder().build().unwrap();
+  assert!(changes.iter().all(|change| change.kind() == ChangeKind::Unchanged));
+  assert_eq!(fs::read_to_string(&main).unwrap(), content);
+  assert!(project.join(".omnidux/notes.txt").exists());
+
+  // Planning reports changes without writing them.
+  let cargo_toml = project.join(".omnidux/empty-main-lib/Cargo.toml");
+  fs::write(&cargo_toml, "outdated").unwrap();
+  let changes = builder().plan().unwrap();
+  let change = changes.iter().find(|change| change.path == cargo_toml).unwrap();
+  assert_eq!(change.kind(), ChangeKind::Update);
+  assert!(change.diff().starts_with(&format!("--- a/{p}\n+++ b/{p}\n@@ -1 +1,", p = cargo_toml.display())));
+  assert_eq!(fs::read_to_string(&cargo_toml).unwrap(), "outdated");
+}
+
+#[test]
+fn generator_deletes_entry_files_of_other_platforms() {
+  let project = temp_folder("generator-platforms");
+  let builder = |platform: Platform| {
+    let mut config = empty_config();
+    config.target.platform = platform;
+    RustProjectBuilder::new("empty".to_string())
+      .set_destination(project.join(".omnidux").to_string_lossy().into_owned())
+      .set_config(config)
+  };
+  let crate_path = project.join(".omnidux/empty-main-lib");
+
+  builder(Platform::Test).build().unwrap();
+  fs::write(crate_path.join("src/notes.rs"), "// kept").unwrap();
+
+  // Switching to a library target removes the stale main.rs.
+  let changes = builder(Platform::Web).build().unwrap();
+  let deleted: Vec<&PathBuf> = changes.iter()
+    .filter(|change| change.kind() == ChangeKind::Delete)
+    .map(|change| &change.path)
+    .collect();
+  assert_eq!(deleted, vec![&crate_path.join("src/main.rs")]);
+  assert!(!crate_path.join("src/main.rs").exists());
+  assert!(crate_path.join("src/lib.rs").exists());
+  assert!(crate_path.join("js/workers.js").exists());
+  assert!(crate_path.join("src/notes.rs").exists());
+
+  // Files that were not generated by omnidux are never deleted.
+  fs::write(crate_path.join("src/main.rs"), "fn main() {}").unwrap();
+  let changes = builder(Platform::IOS).plan().unwrap();
+  let deleted: Vec<&PathBuf> = changes.iter()
+    .filter(|change| change.kind() == ChangeKind::Delete)
+    .map(|change| &change.path)
+    .collect();
+  assert_eq!(deleted, vec![&crate_path.join("js/workers.js")]);
+  let diff = changes.iter().find(|change| change.kind() == ChangeKind::Delete).unwrap().diff();
+  assert!(diff.contains("+++ /dev/null\n"));
+}
+
+#[test]
+fn generator_creates_unified_diffs() {
+  let old = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\nk\nl\nm\nn\n";
+  let new = "a\nb\nc\nD\ne\nf\ng\nh\ni\nj\nk\nl\nm\nn\no\n";
+
+  assert_eq!(unified_diff(Some(old), old, "file"), "");
+  assert_eq!(
+    unified_diff(Some(old), new, "file"),
+    "--- a/file\n+++ b/file\n@@ -1,7 +1,7 @@\n a\n b\n c\n-d\n+D\n e\n f\n g\n@@ -12,3 +12,4 @@\n l\n m\n n\n+o\n",
+  );
+  assert_eq!(unified_diff(None, "a\n", "file"), "--- /dev/null\n+++ b/file\n@@ -0,0 +1 @@\n+a\n");
+}
+
+/// Lists the files of the generated crate for the given platform followed by their
+/// content, the configuration snapshot is the same for every platform.
+fn platform_layout(platform: Platform, generator: Box<dyn PlatformGenerator>) -> String {
+  let mut config = build_config_from_file(format!("{}/omnidux.test.yaml", PROJECT)).unwrap();
+  config.target.platform = platform;
+  let crate_path = Path::new(PROJECT).join(".omnidux/generator-main-lib");
+
+  let changes = builder()
+    .set_config(config)
+    .set_platform_generator(generator)
+    .plan()
+    .unwrap();
+
+  let mut layout = String::new();
+  for change in &changes {
+    layout.push_str(&format!("{}\n", change.path.strip_prefix(&crate_path).unwrap().display()));
+  }
+  for change in changes.iter().filter(|change| !change.path.ends_with("omnidux.yaml")) {
+    layout.push_str(&format!("\n== {} ==\n{}", change.path.strip_prefix(&crate_path).unwrap().display(), change.content));
+  }
+  layout
+}
+
+#[test]
+fn generator_test_platform_layout() {
+  assert_snapshot!(platform_layout(Platform::Test, Box::new(TestGenerator)));
+}
+
+#[test]
+fn generator_ios_platform_layout() {
+  assert_snapshot!(platform_layout(Platform::IOS, Box::new(IOSGenerator)));
+}
+
+#[test]
+fn generator_web_platform_layout() {
+  assert_snapshot!(platform_layout(Platform::Web, Box::new(WebGenerator)));
+}
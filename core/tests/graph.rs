@@ -0,0 +1,70 @@
+extern crate omnidux_core;
+extern crate insta;
+
+use std::path::Path;
+
+use insta::assert_snapshot;
+
+use omnidux_core::config::build_config_from_file;
+use omnidux_core::config::manifest::CapsuleRequirement;
+use omnidux_core::graph::{detect_capsule_reads, render, CapsuleRead, GraphFormat};
+
+const PROJECT: &str = "tests/fixtures/generator";
+
+#[test]
+fn graph_renders_dot() {
+  let config = build_config_from_file(format!("{}/omnidux.test.yaml", PROJECT)).unwrap();
+  let reads = detect_capsule_reads(&config, Path::new(PROJECT));
+
+  assert_snapshot!(render(&config, GraphFormat::Dot, &reads));
+}
+
+#[test]
+fn graph_renders_mermaid() {
+  let config = build_config_from_file(format!("{}/omnidux.test.yaml", PROJECT)).unwrap();
+  let reads = detect_capsule_reads(&config, Path::new(PROJECT));
+
+  assert_snapshot!(render(&config, GraphFormat::Mermaid, &reads));
+}
+
+#[test]
+fn graph_detects_capsule_reads() {
+  let config = build_config_from_file(format!("{}/omnidux.test.yaml", PROJECT)).unwrap();
+  assert_eq!(detect_capsule_reads(&config, Path::new(PROJECT)), vec![CapsuleRead {
+    reader: "widgets".to_string(),
+    owner: "app".to_string(),
+    capsule: "AppCapsule".to_string(),
+  }]);
+
+  // Requirements declared by the manifests count as reads as well.
+  let mut config = config;
+  config.manifests.get_mut("app").unwrap().requires.push(CapsuleRequirement {
+    repo: "widgets".to_string(),
+    capsule: "WidgetCapsule".to_string(),
+  });
+  let reads: Vec<String> = detect_capsule_reads(&config, Path::new(PROJECT)).into_iter()
+    .map(|read| format!("{} -> {}::{}", read.reader, read.owner, read.capsule))
+    .collect();
+  assert_eq!(reads, vec!["app -> widgets::WidgetCapsule", "widgets -> app::AppCapsule"]);
+}
+
+#[test]
+fn graph_escapes_labels() {
+  let mut config = build_config_from_file(format!("{}/omnidux.test.yaml", PROJECT)).unwrap();
+  config.name = "say \"hi\"".to_string();
+  config.threads[0].name = "ui <\"main\"> #1".to_string();
+  let reads = vec![CapsuleRead {
+    reader: "widgets".to_string(),
+    owner: "app".to_string(),
+    capsule: "Path\\\"Capsule".to_string(),
+  }];
+
+  let dot = render(&config, GraphFormat::Dot, &reads);
+  assert!(dot.starts_with("digraph \"say \\\"hi\\\"\" {"));
+  assert!(dot.contains("[label=\"ui <\\\"main\\\"> #1\\nmpsc-fifo\", shape=box];"));
+  assert!(dot.contains("[label=\"Path\\\\\\\"Capsule\", style=dashed];"));
+
+  let mermaid = render(&config, GraphFormat::Mermaid, &reads);
+  assert!(mermaid.contains("[[\"ui #lt;#quot;main#quot;#gt; #35;1 (mpsc-fifo)\"]]"));
+  assert!(mermaid.contains(" -.->|\"Path\\#quot;Capsule\"| "));
+}
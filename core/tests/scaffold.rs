@@ -0,0 +1,105 @@
+extern crate omnidux_core;
+extern crate insta;
+
+mod common;
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use insta::assert_snapshot;
+
+use omnidux_core::config::{build_config_from_file, Platform, ThreadDriver, ThreadType};
+use omnidux_core::generator::files::ChangeKind;
+use omnidux_core::generator::scaffold::{new_project, new_repo};
+
+use common::temp_folder;
+
+fn omnidux_path() -> PathBuf {
+  Path::new("..").canonicalize().unwrap()
+}
+
+#[test]
+fn scaffold_creates_project_and_repo() {
+  let parent = temp_folder("scaffold-project");
+
+  for change in new_project(&parent, "todo", &Platform::Web, &omnidux_path()).unwrap() {
+    assert_eq!(change.kind(), ChangeKind::Create);
+    change.apply().unwrap();
+  }
+  let config_file = parent.join("todo/omnidux.web.yaml");
+  let config = build_config_from_file(config_file.to_string_lossy().into_owned()).unwrap();
+  assert_eq!(config.target.engine, "wasm");
+  assert_eq!(config.threads[1].thread_type, ThreadType::WebWorker);
+  assert_eq!(config.threads[1].driver, ThreadDriver::WorkerDirect);
+  assert_eq!(config.repos[0].name(), "core");
+  assert!(parent.join("todo/repos/core/src/lib.rs").exists());
+
+  let changes = new_repo(&config_file, "todo-list", &omnidux_path()).unwrap();
+  for change in &changes {
+    change.apply().unwrap();
+  }
+
+  let config = build_config_from_file(config_file.to_string_lossy().into_owned()).unwrap();
+  assert_eq!(config.repos[1].name(), "todo-list");
+  assert_eq!(config.setup[1].repo, "todo-list");
+  assert_eq!(config.setup[1].target_threads, vec![1]);
+  assert_eq!(config.scopes[1].repo, "todo-list");
+  assert_eq!(config.manifests["todo-list"].crate_name, "todo_todo_list");
+
+  let lib = fs::read_to_string(parent.join("todo/repos/todo-list/src/lib.rs")).unwrap();
+  assert_snapshot!("scaffold_repo_lib", lib);
+  let config_str = fs::read_to_string(&config_file).unwrap();
+  assert_snapshot!("scaffold_project_config", config_str);
+
+  // Neither files nor registrations are created twice.
+  assert!(new_repo(&config_file, "todo-list", &omnidux_path()).is_err());
+  assert!(new_project(&parent, "todo", &Platform::Web, &omnidux_path()).is_err());
+}
+
+#[test]
+fn scaffold_uses_native_threads_outside_of_the_web() {
+  let parent = temp_folder("scaffold-native");
+
+  let changes = new_project(&parent, "todo", &Platform::IOS, &omnidux_path()).unwrap();
+  let config_file = changes.iter().find(|change| change.path.ends_with("omnidux.ios.yaml")).unwrap();
+  assert!(config_file.content.contains("  engine: native\n"));
+  assert!(config_file.content.contains("    - type: thread\n      name: worker1\n      driver: mpsc-fifo\n"));
+  assert!(config_file.content.contains("    - repo: core\n      target: worker1"));
+}
+
+#[test]
+fn scaffold_registers_repo_in_commented_legacy_config() {
+  let project = temp_folder("scaffold-legacy");
+  let config_file = project.join("omnidux.ios.yaml");
+  fs::write(&config_file, "\
+name: legacy
+target:
+  platform: ios
+  engine: native
+
+repos:
+- type: local
+  source: ./repos/core
+
+scopes:
+  - repo: core
+
+threads:
+  - type: main
+    name: main
+    driver: direct
+
+setup:
+  - repo: core
+    target: main
+
+#  - repo: board
+#    target: main
+").unwrap();
+
+  let changes = new_repo(&config_file, "board", &omnidux_path()).unwrap();
+  let update = changes.iter().find(|change| change.path == config_file).unwrap();
+  assert_eq!(update.kind(), ChangeKind::Update);
+  assert_snapshot!(update.content);
+}
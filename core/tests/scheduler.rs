@@ -0,0 +1,687 @@
+#[macro_use]
+extern crate omnidux_core;
+
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use futures::channel::oneshot;
+
+use omnidux_core::config::{build_config_from_str, Configuration, ResolvedScale, TaskPriority, ThreadDriver};
+use omnidux_core::repo::Repository;
+use omnidux_core::scheduler::context::Context;
+use omnidux_core::scheduler::strategy::ScheduleStrategy;
+use omnidux_core::scheduler::timer::{Clock, ManualClock};
+use omnidux_core::task::{Task, TaskError};
+use omnidux_core::threads::Thread;
+use omnidux_core::threads::priority::PriorityQueue;
+
+#[allow(dead_code, non_snake_case)]
+mod counter {
+  use omnidux_core::scheduler::strategy::{
+    ScheduleStrategy, take_first, round_robin, least_loaded, random, key_affinity, broadcast,
+  };
+  use std::sync::{Arc, Mutex};
+
+  use futures::channel::oneshot;
+  use futures::future::{self, FutureExt};
+  use omnidux_core::task::{AsyncTaskHandler, HandlerFuture, TaskHandler, Task};
+  use omnidux_core::capsule::CapsuleContent;
+
+  impl_default_capsule! (CountCapsule, usize, usize, inspectable);
+
+  #[derive(Clone)]
+  pub struct Count { uuid: usize }
+  impl TaskHandler for Count {
+    type Input = ();
+    type Output = ();
+
+    fn handle(&self, _task: &Task, _input: &()) {}
+  }
+  impl_strategy! (Count, take_first);
+
+  macro_rules! noop_task {
+    ($name:ident, $strategy:ident) => {
+      #[derive(Clone)]
+      pub struct $name { uuid: usize }
+      impl TaskHandler for $name {
+        type Input = ();
+        type Output = ();
+
+        fn handle(&self, _task: &Task, _input: &()) {}
+      }
+      impl_strategy! ($name, $strategy);
+    };
+  }
+
+  noop_task! (RoundRobin, round_robin);
+  noop_task! (LeastLoaded, least_loaded);
+  noop_task! (Random, random);
+  noop_task! (Affinity, key_affinity);
+  noop_task! (Broadcast, broadcast);
+
+  #[derive(Clone)]
+  pub struct Rename { uuid: usize }
+  impl TaskHandler for Rename {
+    type Input = String;
+    type Output = ();
+
+    fn handle(&self, _task: &Task, input: &String) {
+      assert!(!input.is_empty());
+    }
+  }
+  impl_strategy! (Rename, take_first);
+
+  #[derive(Clone)]
+  pub struct Double { uuid: usize }
+  impl TaskHandler for Double {
+    type Input = usize;
+    type Output = usize;
+
+    fn handle(&self, _task: &Task, input: &usize) -> usize {
+      input * 2
+    }
+  }
+  impl_strategy! (Double, take_first);
+
+  #[derive(Clone)]
+  pub struct Fail { uuid: usize }
+  impl TaskHandler for Fail {
+    type Input = ();
+    type Output = ();
+
+    fn handle(&self, _task: &Task, _input: &()) {
+      panic!("board is locked");
+    }
+  }
+  impl_strategy! (Fail, take_first);
+
+  /// Receiver of the value an `Await` task waits for.
+  pub type Gate = Mutex<Option<oneshot::Receiver<usize>>>;
+
+  #[derive(Clone)]
+  pub struct Await { uuid: usize }
+  impl AsyncTaskHandler for Await {
+    type Input = Gate;
+    type Output = usize;
+
+    fn handle(&self, _task: &Task, input: Arc<Gate>) -> HandlerFuture<usize> {
+      let receiver = input.lock().unwrap().take().unwrap();
+      receiver.map(|value| value.unwrap_or(0) + 1).boxed()
+    }
+  }
+  impl_strategy! (Await, take_first);
+  impl_async_task! (Await);
+
+  #[derive(Clone)]
+  pub struct Explode { uuid: usize }
+  impl AsyncTaskHandler for Explode {
+    type Input = ();
+    type Output = ();
+
+    fn handle(&self, _task: &Task, _input: Arc<()>) -> HandlerFuture<()> {
+      future::lazy(|_| panic!("connection reset")).boxed()
+    }
+  }
+  impl_strategy! (Explode, take_first);
+  impl_async_task! (Explode);
+
+  create_repo! {
+    tasks: [
+      Count,
+      RoundRobin,
+      LeastLoaded,
+      Random,
+      Affinity,
+      Broadcast,
+      Rename,
+      Double,
+      Fail,
+      Await,
+      Explode,
+    ],
+    capsules: [
+      CountCapsule,
+    ]
+  }
+}
+
+fn config(target: &str) -> Configuration {
+  build_config_from_str(&format!("
+name: scheduler
+target:
+  platform: test
+  engine: native
+repos:
+  - type: local
+    source: ./counter
+threads:
+  - type: main
+    name: main
+    driver: direct
+  - type: thread
+    name: worker1
+    driver: mpsc-fifo
+  - type: thread
+    name: worker2
+    driver: mpsc-fifo
+setup:
+  - repo: counter
+    target: {}
+", target)).unwrap()
+}
+
+/// Boots the counter repo without spawning the threads, tasks are handled by polling them.
+fn boot(config: &Configuration) -> (Context, Vec<Thread>, Arc<Mutex<Vec<usize>>>) {
+  let mut counter = 0usize;
+  let repos: Vec<Arc<dyn Repository + Send + Sync>> = vec![
+    Arc::new(counter::Repository::new(config.setup[0].clone(), &mut counter)),
+  ];
+
+  let threads: Vec<Thread> = config.threads.iter()
+    .enumerate()
+    .map(|(i, thread)| Thread::new(i, thread.clone()))
+    .collect();
+  let senders = threads.iter().map(|thread| thread.create_sender()).collect();
+
+  let handled = Arc::new(Mutex::new(Vec::new()));
+  let log = handled.clone();
+  let mut context = Context::new(repos, senders);
+  context.set_execution_listener(Arc::new(move |thread, _task: &Task| log.lock().unwrap().push(thread)));
+
+  (context, threads, handled)
+}
+
+fn count_task() -> Task {
+  Task {
+    execution_targets: None,
+    uuid: 0,
+    payload: Arc::new(()),
+    key: None,
+    priority: None,
+    completion: None,
+  }
+}
+
+#[test]
+fn scheduler_routes_tasks_to_target_threads() {
+  let config = config("\"*\"");
+  let (context, mut threads, handled) = boot(&config);
+
+  context.schedule_on(count_task(), vec![2]);
+  context.schedule_on(count_task(), vec![0, 2, 2]);
+
+  let polled: Vec<usize> = threads.iter_mut().map(|thread| thread.poll(&context)).collect();
+  assert_eq!(polled, vec![1, 0, 2]);
+  assert_eq!(*handled.lock().unwrap(), vec![0, 2, 2]);
+}
+
+#[test]
+fn scheduler_routes_by_strategy() {
+  let config = config("\"*\"");
+  let (context, mut threads, handled) = boot(&config);
+
+  context.schedule::<counter::Count>(count_task());
+
+  let polled: Vec<usize> = threads.iter_mut().map(|thread| thread.poll(&context)).collect();
+  assert_eq!(polled, vec![1, 0, 0]);
+  assert_eq!(*handled.lock().unwrap(), vec![0]);
+}
+
+#[test]
+fn scheduler_maps_target_names_to_threads() {
+  let config = config("worker2");
+  let (context, mut threads, handled) = boot(&config);
+
+  let eligible = context.get_eligible_threads(0);
+  assert_eq!(eligible.uuids(), vec![2]);
+  assert_eq!(eligible.find("worker2").map(|thread| thread.uuid), Some(2));
+  assert!(eligible.find("worker1").is_none());
+
+  // The first eligible thread is the configured one, not the first thread overall.
+  context.schedule::<counter::Count>(count_task());
+
+  let polled: Vec<usize> = threads.iter_mut().map(|thread| thread.poll(&context)).collect();
+  assert_eq!(polled, vec![0, 0, 1]);
+  assert_eq!(*handled.lock().unwrap(), vec![2]);
+}
+
+/// Threads picked by the strategy of `T` for the given task.
+fn pick<T: ScheduleStrategy>(config: &Configuration, context: &Context, task: &Task) -> Vec<usize> {
+  T::find_preferred_target(&config.setup[0], context.get_eligible_threads(0), task)
+}
+
+#[test]
+fn scheduler_strategies_spread_tasks() {
+  let config = config("[worker1, worker2]");
+  let (context, mut threads, _) = boot(&config);
+  let task = count_task();
+
+  let turns: Vec<Vec<usize>> = (0..4).map(|_| pick::<counter::RoundRobin>(&config, &context, &task)).collect();
+  assert_eq!(turns, vec![vec![1], vec![2], vec![1], vec![2]]);
+
+  assert_eq!(pick::<counter::Broadcast>(&config, &context, &task), vec![1, 2]);
+
+  // The queue of worker1 is deeper until it handled its task.
+  assert_eq!(pick::<counter::LeastLoaded>(&config, &context, &task), vec![1]);
+  context.schedule_on(count_task(), vec![1]);
+  assert_eq!(pick::<counter::LeastLoaded>(&config, &context, &task), vec![2]);
+  threads[1].poll(&context);
+  assert_eq!(pick::<counter::LeastLoaded>(&config, &context, &task), vec![1]);
+}
+
+#[test]
+fn scheduler_random_strategy_is_seedable() {
+  let config = config("[worker1, worker2]");
+  let (context, _, _) = boot(&config);
+  let task = count_task();
+  let state = <counter::Random as ScheduleStrategy>::strategy_state();
+
+  state.seed(7);
+  let first: Vec<Vec<usize>> = (0..16).map(|_| pick::<counter::Random>(&config, &context, &task)).collect();
+  state.seed(7);
+  let second: Vec<Vec<usize>> = (0..16).map(|_| pick::<counter::Random>(&config, &context, &task)).collect();
+
+  assert_eq!(first, second);
+  assert!(first.contains(&vec![1]) && first.contains(&vec![2]));
+}
+
+#[test]
+fn scheduler_limits_repos_to_their_scale() {
+  let config = config("[worker1, worker2]\n    min: cpu\n    max: 1");
+  let (context, _, _) = boot(&config);
+
+  assert_eq!(context.get_scale(0), ResolvedScale { min: 1, max: 1 });
+  assert_eq!(context.get_eligible_threads(0).uuids(), vec![1]);
+  assert_eq!(pick::<counter::Broadcast>(&config, &context, &count_task()), vec![1]);
+}
+
+#[test]
+fn scheduler_key_affinity_keeps_keys_on_one_thread() {
+  let config = config("[worker1, worker2]");
+  let (context, _, _) = boot(&config);
+
+  let board = count_task().with_key(&"board-1");
+  let thread = pick::<counter::Affinity>(&config, &context, &board);
+  for _ in 0..8 {
+    assert_eq!(pick::<counter::Affinity>(&config, &context, &board), thread);
+  }
+
+  let picked: Vec<Vec<usize>> = (0..16)
+    .map(|i| pick::<counter::Affinity>(&config, &context, &count_task().with_key(&i)))
+    .collect();
+  assert!(picked.contains(&vec![1]) && picked.contains(&vec![2]));
+
+  // Tasks without key stay on the first thread.
+  assert_eq!(pick::<counter::Affinity>(&config, &context, &count_task()), vec![1]);
+}
+
+#[test]
+fn scheduler_schedules_keyed_tasks() {
+  let config = config("[worker1, worker2]");
+  let (mut context, mut threads, _) = boot(&config);
+
+  let keys = Arc::new(Mutex::new(Vec::new()));
+  let log = keys.clone();
+  context.set_execution_listener(Arc::new(move |thread, task: &Task| {
+    log.lock().unwrap().push((thread, task.key));
+  }));
+
+  schedule_task!(context, counter, Affinity, (), key = "board-1");
+  schedule_task!(context, counter, Affinity, (), key = "board-1");
+  for thread in &mut threads {
+    thread.poll(&context);
+  }
+
+  let key = count_task().with_key(&"board-1").key;
+  let thread = pick::<counter::Affinity>(&config, &context, &count_task().with_key(&"board-1"))[0];
+  assert_eq!(*keys.lock().unwrap(), vec![(thread, key), (thread, key)]);
+}
+
+#[test]
+fn scheduler_hands_typed_payloads_to_handlers() {
+  let config = config("main");
+  let (mut context, mut threads, _) = boot(&config);
+
+  let names = Arc::new(Mutex::new(Vec::new()));
+  let log = names.clone();
+  context.set_execution_listener(Arc::new(move |_, task: &Task| {
+    log.lock().unwrap().push(task.input::<String>().cloned());
+  }));
+
+  schedule_task!(context, counter, Rename, "Backlog".to_string());
+  schedule_task!(context, counter, Count);
+  threads[0].poll(&context);
+
+  assert_eq!(*names.lock().unwrap(), vec![Some("Backlog".to_string()), None]);
+}
+
+#[test]
+fn scheduler_reports_payload_mismatches() {
+  let config = config("main");
+  let (context, _, handled) = boot(&config);
+
+  let rename = Task {
+    execution_targets: Some(vec![0]),
+    uuid: 6,
+    payload: Arc::new(42usize),
+    key: None,
+    priority: None,
+    completion: None,
+  };
+  assert_eq!(context.handle_schedule(0, &rename).err(), Some(TaskError::PayloadMismatch {
+    task: 6,
+    expected: "alloc::string::String",
+  }));
+
+  let unknown = Task { uuid: 99, ..rename };
+  assert_eq!(context.handle_schedule(0, &unknown).err(), Some(TaskError::UnknownTask(99)));
+  assert!(handled.lock().unwrap().is_empty());
+}
+
+#[test]
+fn scheduler_resolves_futures_with_task_outputs() {
+  let config = config("worker1");
+  let (context, mut threads, _) = boot(&config);
+  threads[1].spawn(&context);
+
+  let doubled = schedule_task!(context, counter, Double, 21);
+  assert_eq!(doubled.wait(), Ok(42));
+
+  let erased = context.schedule_on(count_task(), vec![1]);
+  assert_eq!(erased.downcast::<usize>().wait(), Err(TaskError::OutputMismatch {
+    task: 0,
+    expected: "usize",
+  }));
+}
+
+#[test]
+fn scheduler_reports_panics_through_futures() {
+  let config = config("main");
+  let (context, mut threads, handled) = boot(&config);
+
+  let failed = schedule_task!(context, counter, Fail);
+  let doubled = schedule_task!(context, counter, Double, 2);
+  assert_eq!(threads[0].poll(&context), 2);
+
+  // The thread survives the panic and keeps handling tasks.
+  assert_eq!(failed.wait(), Err(TaskError::Panicked {
+    task: 8,
+    message: "board is locked".to_string(),
+  }));
+  assert_eq!(doubled.wait(), Ok(4));
+  assert_eq!(*handled.lock().unwrap(), vec![0]);
+}
+
+#[test]
+fn scheduler_reports_unknown_target_threads() {
+  let config = config("main");
+  let (context, mut threads, handled) = boot(&config);
+
+  let future = context.schedule_on(count_task(), vec![7]);
+  assert_eq!(future.wait().err(), Some(TaskError::UnknownThread { task: 0, thread: 7 }));
+
+  // Known targets don't receive a task that can't reach all of its targets.
+  let future = context.schedule_on(count_task(), vec![0, 7]);
+  assert_eq!(future.wait().err(), Some(TaskError::UnknownThread { task: 0, thread: 7 }));
+  let polled: usize = threads.iter_mut().map(|thread| thread.poll(&context)).sum();
+  assert_eq!(polled, 0);
+  assert!(handled.lock().unwrap().is_empty());
+}
+
+fn gate() -> (oneshot::Sender<usize>, counter::Gate) {
+  let (sender, receiver) = oneshot::channel();
+  (sender, Mutex::new(Some(receiver)))
+}
+
+#[test]
+fn scheduler_interleaves_async_tasks_on_one_thread() {
+  let config = config("main");
+  let (context, mut threads, handled) = boot(&config);
+  let queue_depth = || context.get_eligible_threads(0).first().unwrap().queue_depth();
+
+  let (open_first, first_gate) = gate();
+  let (open_second, second_gate) = gate();
+  let first = schedule_task!(context, counter, Await, first_gate);
+  let second = schedule_task!(context, counter, Await, second_gate);
+  let doubled = schedule_task!(context, counter, Double, 4);
+
+  // The synchronous task is handled while both async ones are in flight.
+  assert_eq!(threads[0].poll(&context), 3);
+  assert_eq!(doubled.wait(), Ok(8));
+  assert_eq!(queue_depth(), 2);
+
+  open_second.send(20).unwrap();
+  assert_eq!(threads[0].poll(&context), 0);
+  assert_eq!(second.wait(), Ok(21));
+  assert_eq!(queue_depth(), 1);
+
+  open_first.send(10).unwrap();
+  threads[0].poll(&context);
+  assert_eq!(first.wait(), Ok(11));
+  assert_eq!(queue_depth(), 0);
+  assert_eq!(*handled.lock().unwrap(), vec![0, 0, 0]);
+}
+
+#[test]
+fn scheduler_keeps_spawned_threads_responsive_during_async_tasks() {
+  let config = config("worker1");
+  let (context, mut threads, _) = boot(&config);
+  threads[1].spawn(&context);
+
+  let (open, waiting_gate) = gate();
+  let waiting = schedule_task!(context, counter, Await, waiting_gate);
+  assert_eq!(schedule_task!(context, counter, Double, 5).wait(), Ok(10));
+
+  open.send(1).unwrap();
+  assert_eq!(waiting.wait(), Ok(2));
+}
+
+#[test]
+fn scheduler_reports_panics_of_async_tasks() {
+  let config = config("main");
+  let (context, mut threads, handled) = boot(&config);
+
+  let exploded = schedule_task!(context, counter, Explode);
+  threads[0].poll(&context);
+
+  assert_eq!(exploded.wait(), Err(TaskError::Panicked {
+    task: 10,
+    message: "connection reset".to_string(),
+  }));
+  assert_eq!(context.get_eligible_threads(0).first().unwrap().queue_depth(), 0);
+  assert!(handled.lock().unwrap().is_empty());
+}
+
+/// Handles four tasks of different priorities, the repo defaults to idle.
+fn handled_priorities(driver: ThreadDriver) -> Vec<TaskPriority> {
+  let mut config = config("main");
+  config.threads[0].driver = driver;
+  config.setup[0].priority = TaskPriority::Idle;
+  let (mut context, mut threads, _) = boot(&config);
+
+  let priorities = Arc::new(Mutex::new(Vec::new()));
+  let log = priorities.clone();
+  context.set_execution_listener(Arc::new(move |_, task: &Task| log.lock().unwrap().push(task.priority.unwrap())));
+
+  context.schedule::<counter::Count>(count_task());
+  context.schedule::<counter::Count>(count_task().with_priority(TaskPriority::Normal));
+  context.schedule::<counter::Count>(count_task().with_priority(TaskPriority::Immediate));
+  context.schedule::<counter::Count>(count_task().with_priority(TaskPriority::UserBlocking));
+  assert_eq!(threads[0].poll(&context), 4);
+
+  let handled = priorities.lock().unwrap().clone();
+  handled
+}
+
+#[test]
+fn scheduler_serves_urgent_tasks_first() {
+  assert_eq!(handled_priorities(ThreadDriver::Priority), vec![
+    TaskPriority::Immediate,
+    TaskPriority::UserBlocking,
+    TaskPriority::Normal,
+    TaskPriority::Idle,
+  ]);
+
+  // Other drivers keep the order the tasks were received in.
+  assert_eq!(handled_priorities(ThreadDriver::Direct), vec![
+    TaskPriority::Idle,
+    TaskPriority::Normal,
+    TaskPriority::Immediate,
+    TaskPriority::UserBlocking,
+  ]);
+}
+
+#[test]
+fn scheduler_protects_waiting_tasks_from_starvation() {
+  let mut queue = PriorityQueue::new(4);
+  queue.push(count_task().with_priority(TaskPriority::Idle));
+
+  // A steady stream of immediate tasks delays the idle task by three levels of four tasks.
+  let mut popped = Vec::new();
+  for _ in 0..16 {
+    queue.push(count_task().with_priority(TaskPriority::Immediate));
+    popped.push(queue.pop().unwrap().priority.unwrap());
+  }
+
+  assert_eq!(popped.iter().position(|&priority| priority == TaskPriority::Idle), Some(12));
+  assert_eq!(queue.len(), 1);
+}
+
+/// Boots the counter repo on the main thread with timers driven by a manual clock.
+fn boot_with_clock(config: &Configuration) -> (Context, Thread, Arc<Mutex<Vec<usize>>>, Arc<ManualClock>) {
+  let (mut context, mut threads, handled) = boot(config);
+  let clock = Arc::new(ManualClock::new());
+  context.set_clock(clock.clone());
+  (context, threads.remove(0), handled, clock)
+}
+
+#[test]
+fn scheduler_fires_delayed_tasks_once_due() {
+  let config = config("main");
+  let (context, mut main, handled, clock) = boot_with_clock(&config);
+
+  context.schedule_after::<counter::Count>(count_task(), Duration::from_secs(5)).unwrap();
+  context.schedule_at::<counter::Count>(count_task(), clock.now() + Duration::from_secs(10)).unwrap();
+  assert_eq!(context.pending_timers(), 2);
+
+  clock.advance(Duration::from_secs(4));
+  assert_eq!(context.fire_timers(), 0);
+  assert_eq!(main.poll(&context), 0);
+
+  clock.advance(Duration::from_secs(1));
+  assert_eq!(context.fire_timers(), 1);
+  assert_eq!(main.poll(&context), 1);
+
+  clock.advance(Duration::from_secs(20));
+  assert_eq!(context.fire_timers(), 1);
+  assert_eq!(main.poll(&context), 1);
+  assert_eq!(context.pending_timers(), 0);
+  assert_eq!(*handled.lock().unwrap(), vec![0, 0]);
+}
+
+#[test]
+fn scheduler_repeats_periodic_tasks_until_cancelled() {
+  let config = config("main");
+  let (context, mut main, _, clock) = boot_with_clock(&config);
+
+  let every = context.schedule_every::<counter::Count>(count_task(), Duration::from_secs(2)).unwrap();
+  clock.advance(Duration::from_secs(2));
+  assert_eq!(context.fire_timers(), 1);
+
+  // Runs at 4, 6 and 8 seconds were missed, the next one is due at 10 seconds.
+  clock.advance(Duration::from_secs(7));
+  assert_eq!(context.fire_timers(), 1);
+  clock.advance(Duration::from_millis(999));
+  assert_eq!(context.fire_timers(), 0);
+  clock.advance(Duration::from_millis(1));
+  assert_eq!(context.fire_timers(), 1);
+  assert_eq!(main.poll(&context), 3);
+
+  every.cancel();
+  assert!(every.is_cancelled());
+  assert_eq!(context.pending_timers(), 0);
+  clock.advance(Duration::from_secs(2));
+  assert_eq!(context.fire_timers(), 0);
+  assert_eq!(main.poll(&context), 0);
+}
+
+#[test]
+fn scheduler_cancels_delayed_tasks_before_they_are_due() {
+  let config = config("main");
+  let (context, mut main, _, clock) = boot_with_clock(&config);
+
+  let cancelled = context.schedule_after::<counter::Count>(count_task(), Duration::from_secs(1)).unwrap();
+  context.schedule_after::<counter::Count>(count_task(), Duration::from_secs(1)).unwrap();
+  cancelled.cancel();
+
+  clock.advance(Duration::from_secs(1));
+  assert_eq!(context.fire_timers(), 1);
+  assert_eq!(main.poll(&context), 1);
+}
+
+#[test]
+fn scheduler_schedules_timed_tasks_with_payloads() {
+  let config = config("main");
+  let (mut context, mut main, _, clock) = boot_with_clock(&config);
+
+  let names = Arc::new(Mutex::new(Vec::new()));
+  let log = names.clone();
+  context.set_execution_listener(Arc::new(move |_, task: &Task| {
+    log.lock().unwrap().push(task.input::<String>().cloned().unwrap());
+  }));
+
+  schedule_task_after!(context, counter, Rename, "Review".to_string(), Duration::from_secs(1)).unwrap();
+  schedule_task_at!(context, counter, Rename, "Done".to_string(), clock.now() + Duration::from_secs(2)).unwrap();
+  let every = schedule_task_every!(context, counter, Rename, "Standup".to_string(), Duration::from_secs(2)).unwrap();
+
+  clock.advance(Duration::from_secs(1));
+  assert_eq!(context.fire_timers(), 1);
+  clock.advance(Duration::from_secs(1));
+  assert_eq!(context.fire_timers(), 2);
+  every.cancel();
+  assert_eq!(main.poll(&context), 3);
+
+  assert_eq!(*names.lock().unwrap(), vec!["Review", "Done", "Standup"]);
+}
+
+#[test]
+fn scheduler_rejects_invalid_timers() {
+  let config = config("main");
+  let (context, mut main, _, clock) = boot_with_clock(&config);
+  let unknown = || Task { uuid: 99, ..count_task() };
+
+  assert_eq!(
+    context.schedule_after::<counter::Count>(unknown(), Duration::from_secs(1)).err(),
+    Some(TaskError::UnknownTask(99)),
+  );
+  assert_eq!(
+    context.schedule_every::<counter::Count>(count_task(), Duration::from_secs(0)).err(),
+    Some(TaskError::ZeroInterval(0)),
+  );
+  assert_eq!(context.pending_timers(), 0);
+
+  // Tasks no repo owns fail their future instead of panicking the caller.
+  assert_eq!(context.schedule::<counter::Count>(unknown()).wait(), Err(TaskError::UnknownTask(99)));
+
+  context.schedule_after::<counter::Count>(count_task(), Duration::from_secs(1)).unwrap();
+  clock.advance(Duration::from_secs(1));
+  assert_eq!(context.fire_timers(), 1);
+  assert_eq!(main.poll(&context), 1);
+}
+
+#[test]
+fn scheduler_fires_timers_on_the_timer_thread() {
+  let config = config("worker1");
+  let (context, mut threads, handled) = boot(&config);
+  threads[1].spawn(&context);
+  context.spawn_timers();
+
+  context.schedule_after::<counter::Count>(count_task(), Duration::from_millis(10)).unwrap();
+
+  let deadline = Instant::now() + Duration::from_secs(5);
+  while handled.lock().unwrap().is_empty() && Instant::now() < deadline {
+    thread::sleep(Duration::from_millis(1));
+  }
+  assert_eq!(*handled.lock().unwrap(), vec![1]);
+}
@@ -0,0 +1,153 @@
+extern crate omnidux_core;
+
+mod common;
+
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use omnidux_core::config::build_config_from_str;
+use omnidux_core::source::{plan_repos, resolve_repos, LockedRepo, Lockfile, SourceError, CHECKOUT_FOLDER, LOCK_FILE};
+
+use common::temp_folder;
+
+fn git(directory: &Path, args: &[&str]) -> String {
+  let output = Command::new("git").arg("-C").arg(directory).args(args).output().unwrap();
+  assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+  String::from_utf8_lossy(&output.stdout).trim().to_string()
+}
+
+/// Creates a git repository with a single commit on `main` and returns its head.
+fn create_remote(folder: &Path) -> String {
+  git(folder, &["init", "--quiet", "--initial-branch=main"]);
+  git(folder, &["config", "user.email", "test@omnidux"]);
+  git(folder, &["config", "user.name", "omnidux"]);
+  commit(folder, "1")
+}
+
+fn commit(folder: &Path, content: &str) -> String {
+  fs::create_dir_all(folder.join("board")).unwrap();
+  fs::write(folder.join("board/Cargo.toml"), format!("[package]\nname = \"board\"\nversion = \"0.{}.0\"\n", content)).unwrap();
+  git(folder, &["add", "-A"]);
+  git(folder, &["commit", "--quiet", "-m", content]);
+  git(folder, &["rev-parse", "HEAD"])
+}
+
+#[test]
+fn source_resolves_git_repos() {
+  let remote = temp_folder("remote");
+  let project = temp_folder("project");
+  let first = create_remote(&remote);
+
+  let config = build_config_from_str(&format!("
+name: test
+target:
+  platform: test
+  engine: native
+repos:
+  - type: git
+    url: file://{}
+    branch: main
+    source: board
+threads: []
+setup: []
+", remote.display())).unwrap();
+
+  let resolved = resolve_repos(&config, &project, false).unwrap();
+  assert_eq!(resolved[0].name, "board");
+  assert_eq!(resolved[0].path, Path::new(".omnidux/checkouts/board/board"));
+  assert_eq!(resolved[0].commit, Some(first.clone()));
+  assert!(project.join(&resolved[0].path).join("Cargo.toml").exists());
+  assert_eq!(Lockfile::read(&project).unwrap().repos[0].commit, first);
+
+  // Locked commits are kept until the repo is updated explicitly.
+  let second = commit(&remote, "2");
+  assert_eq!(resolve_repos(&config, &project, false).unwrap()[0].commit, Some(first));
+  assert_eq!(resolve_repos(&config, &project, true).unwrap()[0].commit, Some(second.clone()));
+  assert_eq!(Lockfile::read(&project).unwrap().repos[0].commit, second);
+}
+
+#[test]
+fn source_plans_repos_without_side_effects() {
+  let remote = temp_folder("remote-plan");
+  let project = temp_folder("project-plan");
+  let first = create_remote(&remote);
+
+  let config = build_config_from_str(&format!("
+name: test
+target:
+  platform: test
+  engine: native
+repos:
+  - type: git
+    url: file://{}
+    branch: main
+    source: board
+  - type: local
+    source: ./page
+threads: []
+setup: []
+", remote.display())).unwrap();
+
+  // Nothing is cloned or locked before the repos were resolved once.
+  let planned = plan_repos(&config, &project).unwrap();
+  assert_eq!(planned[0].path, Path::new(".omnidux/checkouts/board/board"));
+  assert_eq!(planned[0].commit, None);
+  assert_eq!(planned[1].path, Path::new("./page"));
+  assert!(!project.join(CHECKOUT_FOLDER).exists());
+  assert!(!project.join(LOCK_FILE).exists());
+
+  // Afterwards the locked commit is planned, even if the remote moved on.
+  resolve_repos(&config, &project, false).unwrap();
+  let lock = fs::read_to_string(project.join(LOCK_FILE)).unwrap();
+  commit(&remote, "2");
+  assert_eq!(plan_repos(&config, &project).unwrap()[0].commit, Some(first.clone()));
+  assert_eq!(fs::read_to_string(project.join(LOCK_FILE)).unwrap(), lock);
+  assert_eq!(git(&project.join(CHECKOUT_FOLDER).join("board"), &["rev-parse", "HEAD"]), first);
+}
+
+#[test]
+fn source_rejects_option_like_arguments() {
+  let remote = temp_folder("remote-options");
+  let project = temp_folder("project-options");
+  create_remote(&remote);
+
+  let build = |url: &str, reference: &str| build_config_from_str(&format!("
+name: test
+target:
+  platform: test
+  engine: native
+repos:
+  - type: git
+    url: {}
+    {}
+    source: board
+threads: []
+setup: []
+", url, reference)).unwrap();
+
+  // Urls are never read as options, so no upload pack is run.
+  let marker = project.join("marker");
+  let url = format!("\"--upload-pack=touch {}\"", marker.display());
+  assert!(resolve_repos(&build(&url, "branch: main"), &project, false).is_err());
+  assert!(!marker.exists());
+
+  let url = format!("file://{}", remote.display());
+  match resolve_repos(&build(&url, "rev: \"--output=x\""), &project, false) {
+    Err(SourceError::InvalidReference(reference)) => assert_eq!(reference, "--output=x"),
+    other => panic!("Expected invalid reference, got {:?}", other),
+  }
+
+  let mut lockfile = Lockfile::default();
+  lockfile.insert(LockedRepo {
+    name: "board".to_string(),
+    url: url.clone(),
+    reference: "branch:main".to_string(),
+    commit: "--orphan=x".to_string(),
+  });
+  lockfile.write(&project).unwrap();
+  match resolve_repos(&build(&url, "branch: main"), &project, false) {
+    Err(SourceError::InvalidReference(commit)) => assert_eq!(commit, "--orphan=x"),
+    other => panic!("Expected invalid reference, got {:?}", other),
+  }
+}
@@ -0,0 +1,84 @@
+extern crate omnidux_core;
+
+mod common;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use omnidux_core::config::build_config_from_file;
+use omnidux_core::watch::{watched_paths, Debouncer, Snapshot, Watcher};
+
+use common::temp_folder;
+
+#[test]
+fn watch_covers_config_layers_and_local_repos() {
+  let file = Path::new("tests/fixtures/layering/omnidux.test.yaml");
+  assert_eq!(watched_paths(file, None), vec![
+    PathBuf::from("tests/fixtures/layering/base.yaml"),
+    PathBuf::from("tests/fixtures/layering/omnidux.test.yaml"),
+  ]);
+
+  let file = Path::new("tests/fixtures/generator/omnidux.test.yaml");
+  let config = build_config_from_file(file.to_string_lossy().into_owned()).unwrap();
+  assert_eq!(watched_paths(file, Some(&config)), vec![
+    PathBuf::from("tests/fixtures/generator/omnidux.test.yaml"),
+    PathBuf::from("tests/fixtures/generator/repos/app"),
+    PathBuf::from("tests/fixtures/generator/repos/widgets"),
+  ]);
+}
+
+#[test]
+fn watch_detects_file_changes() {
+  let folder = temp_folder("watch-snapshot");
+  fs::create_dir_all(folder.join("src")).unwrap();
+  fs::create_dir_all(folder.join("target")).unwrap();
+  fs::write(folder.join("src/lib.rs"), "fn a() {}").unwrap();
+  fs::write(folder.join("src/old.rs"), "").unwrap();
+
+  let paths = vec![folder.clone()];
+  let before = Snapshot::scan(&paths);
+  assert_eq!(Snapshot::scan(&paths).changes(&before), Vec::<PathBuf>::new());
+
+  // Build output is ignored.
+  fs::write(folder.join("target/out"), "").unwrap();
+  fs::write(folder.join("src/lib.rs"), "fn a() { b() }").unwrap();
+  fs::write(folder.join("src/new.rs"), "").unwrap();
+  fs::remove_file(folder.join("src/old.rs")).unwrap();
+
+  assert_eq!(Snapshot::scan(&paths).changes(&before), vec![
+    folder.join("src/lib.rs"),
+    folder.join("src/new.rs"),
+    folder.join("src/old.rs"),
+  ]);
+}
+
+#[test]
+fn watch_debounces_bursts_of_events() {
+  let start = Instant::now();
+  let at = |millis| start + Duration::from_millis(millis);
+  let mut debouncer = Debouncer::new(Duration::from_millis(100));
+
+  debouncer.push(vec![PathBuf::from("a")], at(0));
+  debouncer.push(vec![PathBuf::from("b")], at(80));
+  debouncer.push(Vec::new(), at(150));
+  assert_eq!(debouncer.ready(at(150)), None);
+
+  debouncer.push(vec![PathBuf::from("a")], at(160));
+  assert_eq!(debouncer.ready(at(260)), Some(vec![PathBuf::from("a"), PathBuf::from("b")]));
+  assert_eq!(debouncer.ready(at(400)), None);
+}
+
+#[test]
+fn watch_reports_settled_changes() {
+  let folder = temp_folder("watch-poll");
+  fs::write(folder.join("omnidux.test.yaml"), "name: a").unwrap();
+
+  let start = Instant::now();
+  let mut watcher = Watcher::new(vec![folder.clone()], Duration::from_millis(1), Duration::from_millis(100));
+  assert_eq!(watcher.poll(start), None);
+
+  fs::write(folder.join("omnidux.test.yaml"), "name: ab").unwrap();
+  assert_eq!(watcher.poll(start + Duration::from_millis(10)), None);
+  assert_eq!(watcher.poll(start + Duration::from_millis(110)), Some(vec![folder.join("omnidux.test.yaml")]));
+}
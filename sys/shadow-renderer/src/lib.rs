@@ -9,19 +9,25 @@ use omnidux_core::capsule::CapsuleContent;
 pub mod node;
 pub mod style;
 
-impl_default_capsule! (MyCapsule, usize, usize);
+impl_default_capsule! (MyCapsule, usize, usize, inspectable);
 
 #[derive(Clone)]
 pub struct Task1 { uuid: usize }
 impl TaskHandler for Task1 {
-  fn handle(&self, task: &Task) { println!("Wup wup"); }
+  type Input = ();
+  type Output = ();
+
+  fn handle(&self, task: &Task, _input: &()) { println!("Wup wup"); }
 }
 impl_strategy! (Task1, take_first);
 
 #[derive(Clone)]
 pub struct Task2 { uuid: usize }
 impl TaskHandler for Task2 {
-  fn handle(&self, task: &Task) {}
+  type Input = ();
+  type Output = ();
+
+  fn handle(&self, task: &Task, _input: &()) {}
 }
 
 struct Foo;